@@ -1,7 +1,8 @@
 #![no_std]
 
+use blake2::{digest::consts::U32, Blake2b, Digest};
 use gmeta::{In, InOut, Metadata};
-use gstd::{prelude::*, ActorId, Decode, Encode, TypeInfo};
+use gstd::{errors::ReplyCode, prelude::*, ActorId, Decode, Encode, TypeInfo};
 
 pub struct DaoLightMetadata;
 
@@ -20,11 +21,32 @@ pub struct DaoState {
     pub period_duration: u64,
     pub voting_period_length: u64,
     pub grace_period_length: u64,
+    pub reveal_period_length: u64,
+    pub dilution_bound: u128,
+    pub voting_mode: VotingMode,
+    pub conviction_config: ConvictionConfig,
+    pub optimistic_cap: u128,
+    pub objection_threshold: u128,
+    pub timelock: u64,
+    pub guardians: Vec<ActorId>,
+    pub guardian_threshold: u32,
+    pub guardian_approvals: Vec<(GuardianAction, Vec<ActorId>)>,
+    pub paused: Vec<PausableAction>,
     pub total_shares: u128,
     pub members: Vec<(ActorId, Member)>,
     pub proposal_id: u128,
     pub locked_funds: u128,
+    pub optimistic_exposure: u128,
+    pub balance: u128,
     pub proposals: Vec<(u128, Proposal)>,
+    pub transaction_id: u64,
+    pub transactions: Vec<(u64, Transaction)>,
+    pub streams: Vec<(u128, Stream)>,
+    pub recurring_payments: Vec<(u128, RecurringPayment)>,
+    pub conviction_proposal_id: u128,
+    pub conviction_proposals: Vec<(u128, ConvictionProposal)>,
+    pub nonces: Vec<(ActorId, u64)>,
+    pub queued_exits: Vec<(ActorId, u128)>,
 }
 
 impl DaoState {
@@ -35,6 +57,108 @@ impl DaoState {
     }
 }
 
+/// The conditions the proposals returned by the `proposals_by` and `StateQuery::Proposals`
+/// queries must meet,
+/// the condition set to `None` matches any proposal
+#[derive(Debug, Default, Clone, Decode, Encode, TypeInfo)]
+pub struct ProposalFilter {
+    pub proposer: Option<ActorId>,
+    pub applicant: Option<ActorId>,
+    pub status: Option<ProposalStatus>,
+}
+
+impl ProposalFilter {
+    pub fn matches(&self, proposal: &Proposal) -> bool {
+        (self.proposer.is_none() || self.proposer == Some(proposal.proposer))
+            && (self.applicant.is_none() || self.applicant == Some(proposal.applicant))
+            && (self.status.is_none() || self.status.as_ref() == Some(&proposal.status))
+    }
+}
+
+/// The query answered by the contract with `DaoAction::QueryState`,
+/// so only the requested part of the state is encoded
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub enum StateQuery {
+    /// At most `limit` proposals matching `filter` ordered by ID starting from `offset`
+    /// without their summaries and votes, the offset counts only the matching proposals
+    Proposals {
+        filter: ProposalFilter,
+        offset: u32,
+        limit: u32,
+    },
+    /// At most `limit` members ordered by account starting from `offset`
+    Members { offset: u32, limit: u32 },
+    /// The counts and totals of the DAO
+    Summary,
+}
+
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub enum StateReply {
+    Proposals(Vec<(u128, Proposal)>),
+    Members(Vec<(ActorId, Member)>),
+    Summary(DaoSummary),
+}
+
+/// The counts and totals of the DAO returned by the `summary` state query
+#[derive(Debug, Default, Clone, Decode, Encode, TypeInfo)]
+pub struct DaoSummary {
+    pub member_count: u32,
+    pub total_shares: u128,
+    pub balance: u128,
+    pub locked_funds: u128,
+    pub proposal_count: u128,
+    pub submitted_proposals: u32,
+    pub queued_proposals: u32,
+    pub executed_proposals: u32,
+    pub rejected_proposals: u32,
+    pub vetoed_proposals: u32,
+    pub conviction_proposal_count: u128,
+    pub pending_transactions: u32,
+}
+
+/// Calculates the number of tokens redeemed for `shares` out of `total_shares`
+/// when the DAO holds `balance` tokens.
+/// The result is rounded down, so the remaining dust stays in the DAO treasury.
+pub fn redeemable_funds(shares: u128, total_shares: u128, balance: u128) -> u128 {
+    if total_shares == 0 {
+        return 0;
+    }
+    shares.saturating_mul(balance) / total_shares
+}
+
+#[derive(Debug, Default, Clone, Copy, Decode, Encode, TypeInfo)]
+pub enum VotingMode {
+    /// The vote weight equals the member shares
+    #[default]
+    Linear,
+    /// The vote weight is the integer square root of the member shares
+    Quadratic,
+}
+
+/// Calculates the vote weight of `shares` in the given voting mode
+pub fn vote_weight(shares: u128, mode: VotingMode) -> u128 {
+    match mode {
+        VotingMode::Linear => shares,
+        VotingMode::Quadratic => isqrt(shares),
+    }
+}
+
+/// Calculates the integer square root of `value` rounded down
+pub fn isqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+    // Newton's method starting above the root decreases monotonically to it
+    let mut root = 1u128 << ((129 - value.leading_zeros()) / 2);
+    loop {
+        let next = (root + value / root) / 2;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
 #[derive(Debug, Default, Clone, Decode, Encode, TypeInfo)]
 pub struct Proposal {
     pub proposer: ActorId,
@@ -45,16 +169,481 @@ pub struct Proposal {
     pub amount: u128,
     pub processed: bool,
     pub did_pass: bool,
-    pub details: String,
+    pub status: ProposalStatus,
+    pub eta: u64,
+    pub metadata: ProposalMetadata,
     pub starting_period: u64,
     pub ended_at: u64,
+    /// The end of the reveal period of the commit-reveal proposal
+    pub reveal_ended_at: Option<u64>,
+    /// The voting mode at the submission that weighs the votes and the voting power of the proposal
+    pub voting_mode: VotingMode,
+    /// The commitments of the hidden votes, filled in the state from the commitments stored by the DAO
+    pub vote_commitments: Vec<(ActorId, [u8; 32])>,
+    pub max_total_shares_at_yes_vote: u128,
+    /// The votes in the order they were counted, filled in the state from the votes stored by the DAO
     pub votes_by_member: Vec<(ActorId, Vote)>,
+    pub vote_receipts: Vec<(ActorId, VoteReceipt)>,
+    /// The receipts of the ballots in the order they were counted
+    pub ballot_receipts: Vec<(ActorId, BallotReceipt)>,
+    pub kind: ProposalKind,
+    pub execution: Option<ExecutionResult>,
+    pub action_statuses: Vec<ActionStatus>,
+    pub next_action: u32,
+    pub halted: bool,
+    pub milestone_statuses: Vec<MilestoneStatus>,
+    pub winning_option: Option<u32>,
+}
+
+impl Proposal {
+    /// Returns the actions executed when the proposal passes:
+    /// the actions of the batch proposal or the action of the winning option
+    pub fn actions(&self) -> &[ProposalAction] {
+        match &self.kind {
+            ProposalKind::Batch { actions, .. } => actions,
+            ProposalKind::Choice { options, .. } => self
+                .winning_option
+                .and_then(|option| options.get(option as usize))
+                .and_then(|option| option.action.as_ref())
+                .map(core::slice::from_ref)
+                .unwrap_or(&[]),
+            _ => &[],
+        }
+    }
+
+    /// Returns the proposal without its summary and votes as listed by the paginated queries
+    pub fn preview(mut self) -> Self {
+        self.metadata.summary = String::new();
+        self.vote_commitments = Vec::new();
+        self.votes_by_member = Vec::new();
+        self.vote_receipts = Vec::new();
+        self.ballot_receipts = Vec::new();
+        self
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Decode, Encode, TypeInfo)]
+pub enum ProposalStatus {
+    #[default]
+    Submitted,
+    Rejected,
+    Queued,
+    Executed,
+    Vetoed,
+}
+
+#[derive(Debug, Default, Clone, Decode, Encode, TypeInfo)]
+pub enum ProposalKind {
+    #[default]
+    Funding,
+    Execute {
+        destination: ActorId,
+        payload: Vec<u8>,
+        value: u128,
+        gas_limit: u64,
+    },
+    Batch {
+        actions: Vec<ProposalAction>,
+        halt_on_failure: bool,
+    },
+    /// The grant paid to the applicant in stages,
+    /// each milestone is released by the reviewer or by a batch proposal
+    Milestones {
+        milestones: Vec<Milestone>,
+        reviewer: ActorId,
+    },
+    /// The amount vested to the applicant linearly over `duration`,
+    /// nothing is vested before `cliff` expires
+    Stream { duration: u64, cliff: u64 },
+    /// The payment of `amount` tokens due to the applicant every `interval` for `periods` times
+    Recurring {
+        amount: u128,
+        interval: u64,
+        periods: u32,
+    },
+    /// The text-only proposal that records the opinion of members and moves no funds
+    Signal,
+    /// The funding proposal that passes unless NO votes exceed `objection_threshold`
+    /// percent of the voting power
+    Optimistic { objection_threshold: u128 },
+    /// The proposal with several options, the action of the winning option is executed
+    Choice {
+        options: Vec<ChoiceOption>,
+        method: TallyMethod,
+    },
+}
+
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub struct ChoiceOption {
+    pub label: String,
+    pub action: Option<ProposalAction>,
+}
+
+#[derive(Debug, Clone, Copy, Decode, Encode, TypeInfo)]
+pub enum TallyMethod {
+    /// The option with the largest weight wins, members vote with `Ballot::Single` or `Ballot::Split`
+    Plurality,
+    /// The options with the smallest weight are eliminated until one of them gets the majority,
+    /// members vote with `Ballot::Ranked`
+    InstantRunoff,
+}
+
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub enum Ballot {
+    /// All shares of the member are given to the option
+    Single(u32),
+    /// The shares of the member are split across the options
+    Split(Vec<(u32, u128)>),
+    /// The options in order of preference
+    Ranked(Vec<u32>),
+}
+
+/// The maximum length of `ProposalMetadata::title` in bytes
+pub const MAX_TITLE_LENGTH: usize = 128;
+/// The maximum length of `ProposalMetadata::summary` in bytes
+pub const MAX_SUMMARY_LENGTH: usize = 1024;
+/// The maximum length of `ProposalMetadata::uri` in bytes
+pub const MAX_URI_LENGTH: usize = 256;
+/// The maximum length of `Milestone::details` in bytes
+pub const MAX_MILESTONE_DETAILS_LENGTH: usize = 256;
+
+/// The proposal description, the full text is stored off-chain at `uri`
+#[derive(Debug, Default, Clone, Decode, Encode, TypeInfo)]
+pub struct ProposalMetadata {
+    pub title: String,
+    pub summary: String,
+    pub uri: String,
+    /// The hash of the full text that links the proposal to the off-chain discussion
+    pub content_hash: [u8; 32],
+}
+
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub struct Milestone {
+    pub amount: u128,
+    pub details: String,
+}
+
+#[derive(Debug, Default, Clone, Decode, Encode, TypeInfo)]
+pub enum MilestoneStatus {
+    #[default]
+    Pending,
+    Released,
+    Cancelled,
+}
+
+/// The payout of the executed stream proposal, the stream ID is the proposal ID
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub struct Stream {
+    pub recipient: ActorId,
+    pub amount: u128,
+    pub claimed: u128,
+    pub start: u64,
+    pub duration: u64,
+    pub cliff: u64,
+    pub cancelled_at: Option<u64>,
+}
+
+impl Stream {
+    /// Returns the amount vested by `timestamp`, the vesting stops when the stream is cancelled
+    pub fn vested(&self, timestamp: u64) -> u128 {
+        let timestamp = match self.cancelled_at {
+            Some(cancelled_at) => timestamp.min(cancelled_at),
+            None => timestamp,
+        };
+        let elapsed = timestamp.saturating_sub(self.start);
+        if elapsed < self.cliff {
+            return 0;
+        }
+        if elapsed >= self.duration {
+            return self.amount;
+        }
+        self.amount.saturating_mul(elapsed as u128) / self.duration as u128
+    }
+
+    /// Returns the vested amount that hasn't been claimed yet
+    pub fn claimable(&self, timestamp: u64) -> u128 {
+        self.vested(timestamp).saturating_sub(self.claimed)
+    }
+}
+
+/// The recurring payment of the executed proposal, the payment ID is the proposal ID
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub struct RecurringPayment {
+    pub recipient: ActorId,
+    pub amount: u128,
+    pub interval: u64,
+    pub periods: u32,
+    pub paid_periods: u32,
+    pub start: u64,
+    pub cancelled_at: Option<u64>,
+}
+
+impl RecurringPayment {
+    /// Returns the number of periods that have elapsed by `timestamp`,
+    /// no periods become due after the payment is cancelled
+    pub fn due_periods(&self, timestamp: u64) -> u32 {
+        let timestamp = match self.cancelled_at {
+            Some(cancelled_at) => timestamp.min(cancelled_at),
+            None => timestamp,
+        };
+        let elapsed = timestamp.saturating_sub(self.start) / self.interval;
+        elapsed.min(self.periods as u64) as u32
+    }
+
+    /// Returns the number of due periods that haven't been paid yet
+    pub fn unpaid_periods(&self, timestamp: u64) -> u32 {
+        self.due_periods(timestamp)
+            .saturating_sub(self.paid_periods)
+    }
+}
+
+/// The denominator of the fixed-point fractions in `ConvictionConfig`
+pub const CONVICTION_DENOMINATOR: u128 = 10_000_000;
+
+/// The parameters of the conviction voting pool,
+/// the fractions are expressed in `CONVICTION_DENOMINATOR` units
+#[derive(Debug, Default, Clone, Copy, Decode, Encode, TypeInfo)]
+pub struct ConvictionConfig {
+    /// The length of the period in which the conviction decays once
+    pub period: u64,
+    /// The share of the conviction that remains after a period, must be less than one
+    pub decay: u128,
+    /// The maximum share of the available funds a proposal can request
+    pub max_ratio: u128,
+    /// The share of the total shares that scales the threshold, must be greater than zero
+    pub weight: u128,
+}
+
+impl ConvictionConfig {
+    /// Checks that the period and the weight are positive and the fractions are within their bounds
+    pub fn is_valid(&self) -> bool {
+        self.period > 0
+            && self.weight > 0
+            && self.decay < CONVICTION_DENOMINATOR
+            && self.max_ratio > 0
+            && self.max_ratio <= CONVICTION_DENOMINATOR
+    }
+
+    /// Calculates the conviction required to fund `amount` out of the available `funds`:
+    /// `weight * total_shares / ((1 - decay) * (max_ratio - amount / funds)^2)`.
+    /// Returns `None` if the proposal requests `max_ratio` of the funds or more.
+    pub fn threshold(&self, amount: u128, funds: u128, total_shares: u128) -> Option<u128> {
+        if funds == 0 {
+            return None;
+        }
+        let ratio = amount.saturating_mul(CONVICTION_DENOMINATOR) / funds;
+        if ratio >= self.max_ratio {
+            return None;
+        }
+        let margin = self.max_ratio - ratio;
+        let threshold =
+            self.weight.saturating_mul(total_shares) / (CONVICTION_DENOMINATOR - self.decay).max(1);
+        let threshold = threshold.saturating_mul(CONVICTION_DENOMINATOR) / margin;
+        Some(threshold.saturating_mul(CONVICTION_DENOMINATOR) / margin)
+    }
+
+    // calculates `decay` raised to the power of `periods` in `CONVICTION_DENOMINATOR` units
+    fn decay_pow(&self, mut periods: u64) -> u128 {
+        let mut result = CONVICTION_DENOMINATOR;
+        let mut base = self.decay;
+        while periods > 0 && result > 0 {
+            if periods & 1 == 1 {
+                result = result * base / CONVICTION_DENOMINATOR;
+            }
+            base = base * base / CONVICTION_DENOMINATOR;
+            periods >>= 1;
+        }
+        result
+    }
+}
+
+/// The funding request of the conviction voting pool.
+/// Members stake their shares on the proposal and the conviction grows towards
+/// `total_staked / (1 - decay)` with every period, the proposal can be executed
+/// once the conviction reaches the threshold of the requested amount.
+#[derive(Debug, Default, Clone, Decode, Encode, TypeInfo)]
+pub struct ConvictionProposal {
+    pub proposer: ActorId,
+    pub beneficiary: ActorId,
+    pub amount: u128,
+    pub metadata: ProposalMetadata,
+    pub stakes: Vec<(ActorId, u128)>,
+    pub total_staked: u128,
+    pub conviction: u128,
+    pub last_update: u64,
+    pub executed: bool,
+}
+
+impl ConvictionProposal {
+    /// Returns the conviction accumulated by `timestamp` with the current stakes
+    pub fn conviction_at(&self, config: &ConvictionConfig, timestamp: u64) -> u128 {
+        let periods = timestamp.saturating_sub(self.last_update) / config.period;
+        let decay = config.decay_pow(periods);
+        let remaining = self.conviction.saturating_mul(decay) / CONVICTION_DENOMINATOR;
+        let accumulated = self
+            .total_staked
+            .saturating_mul(CONVICTION_DENOMINATOR - decay)
+            / (CONVICTION_DENOMINATOR - config.decay).max(1);
+        remaining.saturating_add(accumulated)
+    }
+
+    /// Accumulates the conviction of the elapsed periods, should be called before the stakes change
+    pub fn update(&mut self, config: &ConvictionConfig, timestamp: u64) {
+        let periods = timestamp.saturating_sub(self.last_update) / config.period;
+        self.conviction = self.conviction_at(config, timestamp);
+        self.last_update = self
+            .last_update
+            .saturating_add(periods.saturating_mul(config.period));
+    }
+
+    /// Returns the shares staked by `account`
+    pub fn stake_of(&self, account: &ActorId) -> u128 {
+        self.stakes
+            .iter()
+            .find(|(id, _)| id == account)
+            .map(|(_, stake)| *stake)
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub enum ProposalAction {
+    Transfer {
+        recipient: ActorId,
+        amount: u128,
+    },
+    UpdateParameter(DaoParameter),
+    Execute {
+        destination: ActorId,
+        payload: Vec<u8>,
+        value: u128,
+        gas_limit: u64,
+    },
+    ReleaseMilestone {
+        proposal_id: u128,
+        index: u32,
+    },
+    CancelGrant {
+        proposal_id: u128,
+    },
+    CancelStream {
+        stream_id: u128,
+    },
+    CancelRecurringPayment {
+        payment_id: u128,
+    },
+}
+
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub enum DaoParameter {
+    PeriodDuration(u64),
+    VotingPeriodLength(u64),
+    GracePeriodLength(u64),
+    RevealPeriodLength(u64),
+    DilutionBound(u128),
+    Timelock(u64),
+    VotingMode(VotingMode),
+    Conviction(ConvictionConfig),
+    OptimisticCap(u128),
+    ObjectionThreshold(u128),
+}
+
+#[derive(Debug, Default, Clone, Decode, Encode, TypeInfo)]
+pub enum ActionStatus {
+    #[default]
+    Pending,
+    /// The reply and its code are recorded for `ProposalAction::Execute`,
+    /// the other actions have no reply code
+    Succeeded {
+        reply: Vec<u8>,
+        reply_code: Option<ReplyCode>,
+    },
+    Failed {
+        error: String,
+    },
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub enum ExecutionResult {
+    /// The reply and its code telling whether the destination replied itself
+    /// or the reply was sent automatically
+    Success {
+        reply: Vec<u8>,
+        reply_code: ReplyCode,
+    },
+    Failure {
+        error: String,
+    },
 }
 
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
 pub struct Member {
     pub shares: u128,
     pub highest_index_yes_vote: Option<u128>,
+    /// The shares staked on the conviction proposals, they can't be withdrawn by `RageQuit`
+    pub staked: u128,
+}
+
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub struct Transaction {
+    pub kind: TransactionKind,
+    pub from: ActorId,
+    pub to: ActorId,
+    pub amount: u128,
+    pub balance_before: u128,
+    pub attempts: u32,
+}
+
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub enum TransactionKind {
+    Deposit { member: ActorId, share: u128 },
+    ExecuteProposal { proposal_id: u128 },
+    RageQuit { member: ActorId, shares: u128 },
+    ProposalAction { proposal_id: u128, index: u32 },
+    Milestone { proposal_id: u128, index: u32 },
+    Stream { stream_id: u128 },
+    RecurringPayment { payment_id: u128, periods: u32 },
+    ConvictionProposal { proposal_id: u128 },
+}
+
+/// The action performed by the guardians once `guardian_threshold` of them approve it.
+/// The paused and unpaused actions are sorted and deduplicated,
+/// so the guardians approve the same action whatever order they list them in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Decode, Encode, TypeInfo)]
+pub enum GuardianAction {
+    Pause(Vec<PausableAction>),
+    Unpause(Vec<PausableAction>),
+    Veto { proposal_id: u128 },
+    SettleTransaction { tx_id: u64, transferred: bool },
+}
+
+/// The `DaoAction` variants that can be paused by the guardians.
+/// `RageQuit` can't be paused so that members are always able to exit,
+/// and neither can `ProcessProposal`, so the proposals locking the shares of YES voters
+/// are always processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Decode, Encode, TypeInfo)]
+pub enum PausableAction {
+    Deposit,
+    SubmitFundingProposal,
+    SubmitExecuteProposal,
+    SubmitBatchProposal,
+    SubmitMilestoneProposal,
+    SubmitStreamProposal,
+    SubmitRecurringProposal,
+    SubmitSignalProposal,
+    SubmitOptimisticProposal,
+    SubmitChoiceProposal,
+    SubmitBallot,
+    ExecuteProposal,
+    SubmitVote,
+    ResumeExecution,
+    ReleaseMilestone,
+    ClaimStream,
+    ClaimRecurringPayment,
+    SubmitConvictionProposal,
+    StakeConviction,
+    ExecuteConvictionProposal,
 }
 
 #[derive(Debug, Decode, Encode, TypeInfo)]
@@ -68,22 +657,165 @@ pub enum Role {
 pub enum DaoAction {
     Deposit {
         amount: u128,
+        min_shares: u128,
     },
     SubmitFundingProposal {
         applicant: ActorId,
         amount: u128,
         quorum: u128,
-        details: String,
+        metadata: ProposalMetadata,
+    },
+    SubmitExecuteProposal {
+        destination: ActorId,
+        payload: Vec<u8>,
+        value: u128,
+        gas_limit: u64,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    },
+    SubmitBatchProposal {
+        actions: Vec<ProposalAction>,
+        halt_on_failure: bool,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    },
+    SubmitMilestoneProposal {
+        applicant: ActorId,
+        milestones: Vec<Milestone>,
+        reviewer: ActorId,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    },
+    SubmitStreamProposal {
+        applicant: ActorId,
+        amount: u128,
+        duration: u64,
+        cliff: u64,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    },
+    SubmitRecurringProposal {
+        applicant: ActorId,
+        amount: u128,
+        interval: u64,
+        periods: u32,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    },
+    SubmitSignalProposal {
+        metadata: ProposalMetadata,
+        quorum: u128,
+    },
+    SubmitOptimisticProposal {
+        applicant: ActorId,
+        amount: u128,
+        metadata: ProposalMetadata,
     },
     ProcessProposal {
         proposal_id: u128,
     },
+    ExecuteProposal {
+        proposal_id: u128,
+    },
+    VetoProposal {
+        proposal_id: u128,
+    },
+    Pause {
+        actions: Vec<PausableAction>,
+    },
+    Unpause {
+        actions: Vec<PausableAction>,
+    },
+    RevokeGuardianApproval {
+        action: GuardianAction,
+    },
+    SubmitChoiceProposal {
+        options: Vec<ChoiceOption>,
+        method: TallyMethod,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    },
     SubmitVote {
         proposal_id: u128,
         vote: Vote,
     },
+    SubmitVoteWithRationale {
+        proposal_id: u128,
+        vote: Vote,
+        rationale: VoteRationale,
+    },
+    SubmitVoteBySig {
+        proposal_id: u128,
+        vote: Vote,
+        voter: ActorId,
+        nonce: u64,
+        signature: VoteSignature,
+    },
+    SubmitVotes {
+        votes: Vec<(u128, Vote)>,
+        all_or_nothing: bool,
+    },
+    CommitVote {
+        proposal_id: u128,
+        commitment: [u8; 32],
+    },
+    RevealVote {
+        proposal_id: u128,
+        vote: Vote,
+        salt: [u8; 32],
+    },
+    SubmitBallot {
+        proposal_id: u128,
+        ballot: Ballot,
+    },
     RageQuit {
         amount: u128,
+        min_funds: u128,
+    },
+    ClaimExit {
+        min_funds: u128,
+    },
+    ContinueTransaction {
+        tx_id: u64,
+    },
+    SettleTransaction {
+        tx_id: u64,
+        transferred: bool,
+    },
+    ResumeExecution {
+        proposal_id: u128,
+    },
+    CancelExecution {
+        proposal_id: u128,
+    },
+    ReleaseMilestone {
+        proposal_id: u128,
+        index: u32,
+    },
+    ClaimStream {
+        stream_id: u128,
+    },
+    ClaimRecurringPayment {
+        payment_id: u128,
+    },
+    SubmitConvictionProposal {
+        beneficiary: ActorId,
+        amount: u128,
+        metadata: ProposalMetadata,
+    },
+    StakeConviction {
+        proposal_id: u128,
+        amount: u128,
+    },
+    WithdrawConviction {
+        proposal_id: u128,
+        amount: u128,
+    },
+    ExecuteConvictionProposal {
+        proposal_id: u128,
+    },
+    QueryState {
+        query: StateQuery,
     },
 }
 
@@ -99,20 +831,150 @@ pub enum DaoEvent {
         proposal_id: u128,
         amount: u128,
     },
+    SubmitExecuteProposal {
+        proposer: ActorId,
+        destination: ActorId,
+        proposal_id: u128,
+    },
+    SubmitBatchProposal {
+        proposer: ActorId,
+        proposal_id: u128,
+        amount: u128,
+    },
+    SubmitMilestoneProposal {
+        proposer: ActorId,
+        applicant: ActorId,
+        proposal_id: u128,
+        amount: u128,
+    },
+    SubmitStreamProposal {
+        proposer: ActorId,
+        applicant: ActorId,
+        proposal_id: u128,
+        amount: u128,
+    },
+    SubmitRecurringProposal {
+        proposer: ActorId,
+        applicant: ActorId,
+        proposal_id: u128,
+        amount: u128,
+    },
+    SubmitSignalProposal {
+        proposer: ActorId,
+        proposal_id: u128,
+    },
+    SubmitOptimisticProposal {
+        proposer: ActorId,
+        applicant: ActorId,
+        proposal_id: u128,
+        amount: u128,
+    },
+    SubmitChoiceProposal {
+        proposer: ActorId,
+        proposal_id: u128,
+        amount: u128,
+    },
     SubmitVote {
         account: ActorId,
         proposal_id: u128,
         vote: Vote,
     },
+    SubmitBallot {
+        account: ActorId,
+        proposal_id: u128,
+    },
+    SubmitVotes {
+        account: ActorId,
+        results: Vec<(u128, VoteResult)>,
+    },
+    CommitVote {
+        account: ActorId,
+        proposal_id: u128,
+    },
     ProcessProposal {
         applicant: ActorId,
         proposal_id: u128,
         did_pass: bool,
     },
+    ExecuteProposal {
+        proposal_id: u128,
+    },
+    VetoProposal {
+        proposal_id: u128,
+    },
+    Pause {
+        actions: Vec<PausableAction>,
+    },
+    Unpause {
+        actions: Vec<PausableAction>,
+    },
+    GuardianApproval {
+        guardian: ActorId,
+        action: GuardianAction,
+        approvals: u32,
+    },
+    RevokeGuardianApproval {
+        guardian: ActorId,
+        action: GuardianAction,
+        approvals: u32,
+    },
     RageQuit {
         member: ActorId,
         amount: u128,
     },
+    RageQuitQueued {
+        member: ActorId,
+        amount: u128,
+    },
+    TransferFailed {
+        recipient: ActorId,
+        amount: u128,
+    },
+    ExecutionHalted {
+        proposal_id: u128,
+        action_index: u32,
+    },
+    CancelExecution {
+        proposal_id: u128,
+        released: u128,
+    },
+    MilestoneReleased {
+        proposal_id: u128,
+        index: u32,
+        amount: u128,
+    },
+    StreamClaimed {
+        stream_id: u128,
+        amount: u128,
+    },
+    RecurringPaymentClaimed {
+        payment_id: u128,
+        periods: u32,
+        amount: u128,
+    },
+    SubmitConvictionProposal {
+        proposer: ActorId,
+        beneficiary: ActorId,
+        proposal_id: u128,
+        amount: u128,
+    },
+    StakeConviction {
+        member: ActorId,
+        proposal_id: u128,
+        amount: u128,
+    },
+    WithdrawConviction {
+        member: ActorId,
+        proposal_id: u128,
+        amount: u128,
+    },
+    ExecuteConvictionProposal {
+        proposal_id: u128,
+        amount: u128,
+    },
+    QueryState {
+        reply: StateReply,
+    },
 }
 
 #[derive(Debug, Decode, Encode, TypeInfo)]
@@ -121,6 +983,15 @@ pub struct InitDao {
     pub voting_period_length: u64,
     pub period_duration: u64,
     pub grace_period_length: u64,
+    pub reveal_period_length: u64,
+    pub dilution_bound: u128,
+    pub voting_mode: VotingMode,
+    pub conviction_config: ConvictionConfig,
+    pub optimistic_cap: u128,
+    pub objection_threshold: u128,
+    pub timelock: u64,
+    pub guardians: Vec<ActorId>,
+    pub guardian_threshold: u32,
 }
 
 #[derive(Debug, Encode, Decode, Clone, TypeInfo)]
@@ -128,3 +999,79 @@ pub enum Vote {
     Yes,
     No,
 }
+
+/// The outcome of the vote submitted with `SubmitVotes`
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub enum VoteResult {
+    Counted { vote: Vote },
+    Rejected { error: String },
+}
+
+/// The maximum length of the vote reason in bytes
+pub const MAX_REASON_LENGTH: usize = 256;
+
+/// The explanation the member attaches to the vote
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub enum VoteRationale {
+    /// The reason stored on-chain, limited by `MAX_REASON_LENGTH`
+    Reason(String),
+    /// The hash of the reason published off-chain
+    Hash([u8; 32]),
+}
+
+/// The record of the vote cast on the proposal
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub struct VoteReceipt {
+    pub vote: Vote,
+    /// The vote weight of the member shares in the voting mode at the time of the vote
+    pub weight: u128,
+    pub timestamp: u64,
+    pub rationale: Option<VoteRationale>,
+    /// The number of votes counted on the proposal before this one
+    pub sequence: u64,
+}
+
+/// The record of the ballot cast on the choice proposal
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub struct BallotReceipt {
+    /// The ballot with the split shares converted to the vote weight
+    pub ballot: Ballot,
+    /// The vote weight of the ballot in the voting mode at the time of the vote
+    pub weight: u128,
+    pub timestamp: u64,
+    /// The number of ballots counted on the proposal before this one
+    pub sequence: u64,
+}
+
+/// The domain separating the signed votes from other messages signed by the member
+pub const VOTE_SIGNING_DOMAIN: &[u8] = b"dao-light:SubmitVoteBySig";
+
+/// The signing context of the sr25519 signatures, the same as used by Substrate wallets
+pub const SR25519_SIGNING_CONTEXT: &[u8] = b"substrate";
+
+/// The signature of the vote made by the key of the voter
+#[derive(Debug, Clone, Decode, Encode, TypeInfo)]
+pub enum VoteSignature {
+    Sr25519([u8; 64]),
+    Ed25519([u8; 64]),
+}
+
+/// Returns the payload the member signs to vote with `SubmitVoteBySig`:
+/// the SCALE encoding of `VOTE_SIGNING_DOMAIN`, the DAO program ID, the proposal ID, the vote and the nonce
+pub fn vote_signing_payload(dao: ActorId, proposal_id: u128, vote: &Vote, nonce: u64) -> Vec<u8> {
+    (VOTE_SIGNING_DOMAIN, dao, proposal_id, vote, nonce).encode()
+}
+
+type Blake2b256 = Blake2b<U32>;
+
+/// Returns the commitment of the hidden vote: the BLAKE2b-256 hash of the SCALE encoding
+/// of the proposal ID, the voter, the vote and the salt.
+/// The commitment is bound to the voter, so it can't be copied by other members.
+pub fn vote_commitment(
+    proposal_id: u128,
+    voter: &ActorId,
+    vote: &Vote,
+    salt: &[u8; 32],
+) -> [u8; 32] {
+    Blake2b256::digest((proposal_id, voter, vote, salt).encode()).into()
+}
@@ -1,7 +1,7 @@
 use crate::ft_messages::*;
 use dao_light_io::*;
-use gstd::{exec, msg, prelude::*, ActorId, String};
-use hashbrown::HashMap;
+use gstd::{errors::ReplyCode, exec, msg, prelude::*, ActorId, MessageId, String};
+use hashbrown::{HashMap, HashSet};
 
 #[derive(Debug, Default)]
 struct Dao {
@@ -9,64 +9,230 @@ struct Dao {
     period_duration: u64,
     voting_period_length: u64,
     grace_period_length: u64,
+    reveal_period_length: u64,
+    dilution_bound: u128,
+    voting_mode: VotingMode,
+    conviction_config: ConvictionConfig,
+    optimistic_cap: u128,
+    objection_threshold: u128,
+    timelock: u64,
+    guardians: HashSet<ActorId>,
+    guardian_threshold: u32,
+    guardian_approvals: HashMap<GuardianAction, HashSet<ActorId>>,
+    paused: HashSet<PausableAction>,
     total_shares: u128,
     members: HashMap<ActorId, Member>,
     proposal_id: u128,
     locked_funds: u128,
+    optimistic_exposure: u128,
+    balance: u128,
     proposals: HashMap<u128, Proposal>,
+    // the votes and ballot receipts are kept out of the proposals,
+    // so that a vote is looked up without scanning the others
+    votes: HashMap<u128, HashMap<ActorId, VoteReceipt>>,
+    vote_commitments: HashMap<u128, HashMap<ActorId, [u8; 32]>>,
+    ballot_receipts: HashMap<u128, HashMap<ActorId, BallotReceipt>>,
+    transaction_id: u64,
+    transactions: HashMap<u64, Transaction>,
+    streams: HashMap<u128, Stream>,
+    recurring_payments: HashMap<u128, RecurringPayment>,
+    conviction_proposal_id: u128,
+    conviction_proposals: HashMap<u128, ConvictionProposal>,
+    nonces: HashMap<ActorId, u64>,
+    queued_exits: HashMap<ActorId, u128>,
 }
 
 impl From<&Dao> for DaoState {
     fn from(dao: &Dao) -> Self {
         let members = dao.members.clone().into_iter().collect();
-        let proposals = dao.proposals.clone().into_iter().collect();
+        let proposals = dao
+            .proposals
+            .iter()
+            .map(|(proposal_id, proposal)| {
+                (
+                    *proposal_id,
+                    dao.proposal_with_votes(*proposal_id, proposal),
+                )
+            })
+            .collect();
+        let transactions = dao.transactions.clone().into_iter().collect();
+        let streams = dao.streams.clone().into_iter().collect();
+        let recurring_payments = dao.recurring_payments.clone().into_iter().collect();
+        let conviction_proposals = dao.conviction_proposals.clone().into_iter().collect();
+        let nonces = dao.nonces.clone().into_iter().collect();
+        let queued_exits = dao.queued_exits.clone().into_iter().collect();
+        let guardian_approvals = dao
+            .guardian_approvals
+            .iter()
+            .map(|(action, guardians)| (action.clone(), guardians.iter().copied().collect()))
+            .collect();
 
         DaoState {
             approved_token_program_id: dao.approved_token_program_id,
             period_duration: dao.period_duration,
             voting_period_length: dao.voting_period_length,
             grace_period_length: dao.grace_period_length,
+            reveal_period_length: dao.reveal_period_length,
+            dilution_bound: dao.dilution_bound,
+            voting_mode: dao.voting_mode,
+            conviction_config: dao.conviction_config,
+            optimistic_cap: dao.optimistic_cap,
+            objection_threshold: dao.objection_threshold,
+            timelock: dao.timelock,
+            guardians: dao.guardians.iter().copied().collect(),
+            guardian_threshold: dao.guardian_threshold,
+            guardian_approvals,
+            paused: dao.paused.iter().copied().collect(),
             total_shares: dao.total_shares,
             members,
             proposal_id: dao.proposal_id,
             locked_funds: dao.locked_funds,
+            optimistic_exposure: dao.optimistic_exposure,
+            balance: dao.balance,
             proposals,
+            transaction_id: dao.transaction_id,
+            transactions,
+            streams,
+            recurring_payments,
+            conviction_proposal_id: dao.conviction_proposal_id,
+            conviction_proposals,
+            nonces,
+            queued_exits,
         }
     }
 }
 
 static mut DAO: Option<Dao> = None;
+// the messages sent by execution proposals that wait for replies and the codes of received replies
+static mut EXECUTION_REPLIES: Option<HashMap<MessageId, Option<ReplyCode>>> = None;
 
 impl Dao {
+    // returns the proposal with the lists of its votes, ballots and commitments for the state queries,
+    // the votes and ballots are listed in the order they were counted
+    fn proposal_with_votes(&self, proposal_id: u128, proposal: &Proposal) -> Proposal {
+        let mut proposal = proposal.clone();
+        if let Some(votes) = self.votes.get(&proposal_id) {
+            let mut receipts: Vec<(ActorId, VoteReceipt)> = votes
+                .iter()
+                .map(|(voter, receipt)| (*voter, receipt.clone()))
+                .collect();
+            receipts.sort_by_key(|(_, receipt)| receipt.sequence);
+            proposal.votes_by_member = receipts
+                .iter()
+                .map(|(voter, receipt)| (*voter, receipt.vote.clone()))
+                .collect();
+            proposal.vote_receipts = receipts;
+        }
+        if let Some(ballots) = self.ballot_receipts.get(&proposal_id) {
+            proposal.ballot_receipts = ballots
+                .iter()
+                .map(|(voter, receipt)| (*voter, receipt.clone()))
+                .collect();
+            proposal
+                .ballot_receipts
+                .sort_by_key(|(_, receipt)| receipt.sequence);
+        }
+        if let Some(commitments) = self.vote_commitments.get(&proposal_id) {
+            proposal.vote_commitments = commitments
+                .iter()
+                .map(|(voter, commitment)| (*voter, *commitment))
+                .collect();
+            proposal.vote_commitments.sort_by_key(|(voter, _)| *voter);
+        }
+        proposal
+    }
+
+    /// Replies with the requested part of the state,
+    /// so the proposals and members are paged and filtered without encoding the whole state
+    /// Arguments:
+    /// * `query`: the state query
+    fn query_state(&self, query: StateQuery) {
+        let reply = match query {
+            StateQuery::Proposals {
+                filter,
+                offset,
+                limit,
+            } => StateReply::Proposals(
+                (0..self.proposal_id)
+                    .filter_map(|id| self.proposals.get(&id).map(|proposal| (id, proposal)))
+                    .filter(|(_, proposal)| filter.matches(proposal))
+                    .skip(offset as usize)
+                    .take(limit as usize)
+                    .map(|(id, proposal)| (id, proposal.clone().preview()))
+                    .collect(),
+            ),
+            StateQuery::Members { offset, limit } => {
+                let mut accounts: Vec<&ActorId> = self.members.keys().collect();
+                accounts.sort();
+                StateReply::Members(
+                    accounts
+                        .into_iter()
+                        .skip(offset as usize)
+                        .take(limit as usize)
+                        .map(|account| (*account, self.members[account].clone()))
+                        .collect(),
+                )
+            }
+            StateQuery::Summary => {
+                let count = |status: ProposalStatus| {
+                    self.proposals
+                        .values()
+                        .filter(|proposal| proposal.status == status)
+                        .count() as u32
+                };
+                StateReply::Summary(DaoSummary {
+                    member_count: self
+                        .members
+                        .values()
+                        .filter(|member| member.shares != 0)
+                        .count() as u32,
+                    total_shares: self.total_shares,
+                    balance: self.balance,
+                    locked_funds: self.locked_funds,
+                    proposal_count: self.proposal_id,
+                    submitted_proposals: count(ProposalStatus::Submitted),
+                    queued_proposals: count(ProposalStatus::Queued),
+                    executed_proposals: count(ProposalStatus::Executed),
+                    rejected_proposals: count(ProposalStatus::Rejected),
+                    vetoed_proposals: count(ProposalStatus::Vetoed),
+                    conviction_proposal_count: self.conviction_proposal_id,
+                    pending_transactions: self.transactions.len() as u32,
+                })
+            }
+        };
+        msg::reply(DaoEvent::QueryState { reply }, 0).unwrap();
+    }
+
     /// Deposits tokens to DAO
+    /// Requirements:
+    /// * No other token transaction of DAO must be in progress
+    /// * The deposit must mint at least `min_shares` shares
+    /// * The DAO balance must grow by exactly `amount` with the confirmed token transfer
     /// Arguments:
     /// * `amount`: the number of fungible tokens that user wants to deposit to DAO
-    async fn deposit(&mut self, amount: u128) {
-        let share = self.calculate_share(amount).await;
-        transfer_tokens(
-            &self.approved_token_program_id,
-            &msg::source(),
-            &exec::program_id(),
-            amount,
-        )
-        .await;
-        self.members
-            .entry(msg::source())
-            .and_modify(|member| member.shares += share)
-            .or_insert(Member {
-                shares: share,
-                highest_index_yes_vote: None,
-            });
+    /// * `min_shares`: the minimum number of shares the user agrees to receive for the deposit
+    async fn deposit(&mut self, amount: u128, min_shares: u128) {
+        let balance = self.update_balance().await;
+        self.check_no_pending_transactions();
 
-        self.total_shares = self.total_shares.saturating_add(share);
-        msg::reply(
-            DaoEvent::Deposit {
+        // token transactions are executed one at a time, so the rate observed before
+        // the transfer holds until the shares are minted after the transfer is confirmed
+        let share = self.calculate_share(amount, balance);
+        if share < min_shares {
+            panic!("Deposit mints {share} shares that is less than {min_shares}");
+        }
+
+        let tx_id = self.start_transaction(
+            TransactionKind::Deposit {
                 member: msg::source(),
                 share,
             },
-            0,
-        )
-        .unwrap();
+            msg::source(),
+            exec::program_id(),
+            amount,
+            balance,
+        );
+        self.execute_transaction(tx_id).await;
     }
 
     /// The proposal of funding
@@ -74,197 +240,2172 @@ impl Dao {
     /// * The proposal can be submitted only by the existing members or their delegate addresses
     /// * The receiver ID can't be the zero
     /// * The DAO must have enough funds to finance the proposal
+    /// * The metadata must not exceed `MAX_TITLE_LENGTH`, `MAX_SUMMARY_LENGTH` and `MAX_URI_LENGTH`
     /// Arguments:
     /// * `receiver`: an actor that will be funded
     /// * `amount`: the number of fungible tokens that will be sent to the receiver
     /// * `quorum`: a certain threshold of YES votes in order for the proposal to pass
-    /// * `details`: the proposal description
+    /// * `metadata`: the proposal title, summary, URI and content hash
     async fn submit_funding_proposal(
         &mut self,
         applicant: &ActorId,
         amount: u128,
         quorum: u128,
-        details: String,
+        metadata: ProposalMetadata,
     ) {
         self.check_for_membership();
+        check_metadata(&metadata);
 
         if applicant.is_zero() {
             panic!("Proposal for the zero address");
         }
 
         // check that DAO has sufficient funds
-        let balance = balance(&self.approved_token_program_id, &exec::program_id()).await;
+        let balance = self.update_balance().await;
         if balance.saturating_sub(self.locked_funds) < amount {
             panic!("Not enough funds in DAO");
         }
 
-        let mut starting_period = exec::block_timestamp();
-        // compute startingPeriod for proposal
-        // there should be a minimum time interval between proposals (period_duration) so that members have time to ragequit
-        if self.proposal_id > 0 {
-            let previous_starting_period = self.proposals[&(self.proposal_id - 1)].starting_period;
-            if starting_period < previous_starting_period + self.period_duration {
-                starting_period = previous_starting_period + self.period_duration;
-            }
+        let proposal_id = self.add_proposal(Proposal {
+            proposer: msg::source(),
+            applicant: *applicant,
+            quorum,
+            amount,
+            metadata,
+            ..Default::default()
+        });
+        self.locked_funds = self.locked_funds.saturating_add(amount);
+
+        msg::reply(
+            DaoEvent::SubmitFundingProposal {
+                proposer: msg::source(),
+                applicant: *applicant,
+                proposal_id,
+                amount,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    /// The proposal to send a message on behalf of DAO
+    /// Requirements:
+    /// * The proposal can be submitted only by the existing members or their delegate addresses
+    /// * The destination can't be the zero address or the approved token program
+    /// Arguments:
+    /// * `destination`: a program the message will be sent to
+    /// * `payload`: the message payload
+    /// * `value`: the value that will be sent with the message
+    /// * `gas_limit`: the gas limit of the message
+    /// * `quorum`: a certain threshold of YES votes in order for the proposal to pass
+    /// * `metadata`: the proposal title, summary, URI and content hash
+    fn submit_execute_proposal(
+        &mut self,
+        destination: &ActorId,
+        payload: Vec<u8>,
+        value: u128,
+        gas_limit: u64,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    ) {
+        self.check_for_membership();
+        check_metadata(&metadata);
+
+        if destination.is_zero() {
+            panic!("Proposal for the zero address");
+        }
+        // DAO tokens can be moved only by funding proposals and ragequits
+        if *destination == self.approved_token_program_id {
+            panic!("Proposal can't send messages to the approved token program");
         }
 
-        let proposal = Proposal {
+        let proposal_id = self.add_proposal(Proposal {
             proposer: msg::source(),
-            applicant: *applicant,
+            applicant: *destination,
+            kind: ProposalKind::Execute {
+                destination: *destination,
+                payload,
+                value,
+                gas_limit,
+            },
             quorum,
+            metadata,
+            ..Default::default()
+        });
+
+        msg::reply(
+            DaoEvent::SubmitExecuteProposal {
+                proposer: msg::source(),
+                destination: *destination,
+                proposal_id,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    /// The proposal of several actions executed one by one
+    /// Requirements:
+    /// * The proposal can be submitted only by the existing members or their delegate addresses
+    /// * The proposal must contain at least one action
+    /// * The actions must be valid: transfers can't be made to the zero address,
+    ///   messages can't be sent to the approved token program, the dilution bound can't be zero
+    /// * The DAO must have enough funds for all transfers of the proposal
+    /// Arguments:
+    /// * `actions`: the actions that will be executed in the given order
+    /// * `halt_on_failure`: whether the execution stops on the failed action until `ResumeExecution`
+    /// * `quorum`: a certain threshold of YES votes in order for the proposal to pass
+    /// * `metadata`: the proposal title, summary, URI and content hash
+    async fn submit_batch_proposal(
+        &mut self,
+        actions: Vec<ProposalAction>,
+        halt_on_failure: bool,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    ) {
+        self.check_for_membership();
+        check_metadata(&metadata);
+
+        if actions.is_empty() {
+            panic!("Proposal without actions");
+        }
+        let amount = actions.iter().fold(0u128, |amount, action| {
+            amount.saturating_add(self.check_action(action))
+        });
+
+        // check that DAO has sufficient funds
+        let balance = self.update_balance().await;
+        if balance.saturating_sub(self.locked_funds) < amount {
+            panic!("Not enough funds in DAO");
+        }
+
+        let proposal_id = self.add_proposal(Proposal {
+            proposer: msg::source(),
             amount,
-            details,
-            starting_period,
-            ended_at: starting_period + self.voting_period_length,
+            quorum,
+            metadata,
+            action_statuses: vec![ActionStatus::Pending; actions.len()],
+            kind: ProposalKind::Batch {
+                actions,
+                halt_on_failure,
+            },
             ..Default::default()
-        };
+        });
+        self.locked_funds = self.locked_funds.saturating_add(amount);
+
+        msg::reply(
+            DaoEvent::SubmitBatchProposal {
+                proposer: msg::source(),
+                proposal_id,
+                amount,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    /// The grant proposal paid to the applicant in several milestones
+    /// The total amount of milestones is locked when the proposal is submitted.
+    /// After the proposal is executed, each milestone is released by the reviewer
+    /// or by a batch proposal with `ProposalAction::ReleaseMilestone`.
+    /// The batch proposal with `ProposalAction::CancelGrant` returns unreleased milestones to the treasury.
+    /// Requirements:
+    /// * The proposal can be submitted only by the existing members or their delegate addresses
+    /// * The applicant can't be the zero address
+    /// * The proposal must contain at least one milestone
+    /// * The milestone details must not exceed `MAX_MILESTONE_DETAILS_LENGTH`
+    /// * The DAO must have enough funds to finance all milestones
+    /// Arguments:
+    /// * `applicant`: an actor that will be funded
+    /// * `milestones`: the amounts of tokens released in stages
+    /// * `reviewer`: an actor that can release milestones, the zero address if only members can
+    /// * `quorum`: a certain threshold of YES votes in order for the proposal to pass
+    /// * `metadata`: the proposal title, summary, URI and content hash
+    async fn submit_milestone_proposal(
+        &mut self,
+        applicant: &ActorId,
+        milestones: Vec<Milestone>,
+        reviewer: &ActorId,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    ) {
+        self.check_for_membership();
+        check_metadata(&metadata);
+
+        if applicant.is_zero() {
+            panic!("Proposal for the zero address");
+        }
+        if milestones.is_empty() {
+            panic!("Proposal without milestones");
+        }
+        if milestones
+            .iter()
+            .any(|milestone| milestone.details.len() > MAX_MILESTONE_DETAILS_LENGTH)
+        {
+            panic!("Milestone details exceed {MAX_MILESTONE_DETAILS_LENGTH} bytes");
+        }
+        let amount = milestones.iter().fold(0u128, |amount, milestone| {
+            amount.saturating_add(milestone.amount)
+        });
+
+        // check that DAO has sufficient funds
+        let balance = self.update_balance().await;
+        if balance.saturating_sub(self.locked_funds) < amount {
+            panic!("Not enough funds in DAO");
+        }
 
-        self.proposals.insert(self.proposal_id, proposal);
+        let proposal_id = self.add_proposal(Proposal {
+            proposer: msg::source(),
+            applicant: *applicant,
+            quorum,
+            amount,
+            metadata,
+            milestone_statuses: vec![MilestoneStatus::Pending; milestones.len()],
+            kind: ProposalKind::Milestones {
+                milestones,
+                reviewer: *reviewer,
+            },
+            ..Default::default()
+        });
+        self.locked_funds = self.locked_funds.saturating_add(amount);
 
         msg::reply(
-            DaoEvent::SubmitFundingProposal {
+            DaoEvent::SubmitMilestoneProposal {
                 proposer: msg::source(),
                 applicant: *applicant,
-                proposal_id: self.proposal_id,
+                proposal_id,
                 amount,
             },
             0,
         )
         .unwrap();
-        self.proposal_id = self.proposal_id.saturating_add(1);
+    }
+
+    /// The funding proposal paid to the applicant linearly over time
+    /// The amount is locked when the proposal is submitted and stays locked until it's claimed.
+    /// The stream starts when the proposal is executed, the applicant claims the vested tokens
+    /// with `ClaimStream`, the batch proposal with `ProposalAction::CancelStream` stops the vesting.
+    /// Requirements:
+    /// * The proposal can be submitted only by the existing members or their delegate addresses
+    /// * The applicant can't be the zero address
+    /// * The duration must be greater than zero and not less than the cliff
+    /// * The DAO must have enough funds to finance the proposal
+    /// Arguments:
+    /// * `applicant`: an actor that will be funded
+    /// * `amount`: the number of fungible tokens vested to the applicant
+    /// * `duration`: the vesting duration
+    /// * `cliff`: the period since the stream start during which nothing is vested
+    /// * `quorum`: a certain threshold of YES votes in order for the proposal to pass
+    /// * `metadata`: the proposal title, summary, URI and content hash
+    async fn submit_stream_proposal(
+        &mut self,
+        applicant: &ActorId,
+        amount: u128,
+        duration: u64,
+        cliff: u64,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    ) {
+        self.check_for_membership();
+        check_metadata(&metadata);
+
+        if applicant.is_zero() {
+            panic!("Proposal for the zero address");
+        }
+        if duration == 0 || cliff > duration {
+            panic!("Invalid stream duration");
+        }
+
+        // check that DAO has sufficient funds
+        let balance = self.update_balance().await;
+        if balance.saturating_sub(self.locked_funds) < amount {
+            panic!("Not enough funds in DAO");
+        }
+
+        let proposal_id = self.add_proposal(Proposal {
+            proposer: msg::source(),
+            applicant: *applicant,
+            quorum,
+            amount,
+            metadata,
+            kind: ProposalKind::Stream { duration, cliff },
+            ..Default::default()
+        });
         self.locked_funds = self.locked_funds.saturating_add(amount);
+
+        msg::reply(
+            DaoEvent::SubmitStreamProposal {
+                proposer: msg::source(),
+                applicant: *applicant,
+                proposal_id,
+                amount,
+            },
+            0,
+        )
+        .unwrap();
     }
 
-    /// The member submit his vote (YES or NO) on the proposal
+    /// The proposal paying the applicant the same amount every interval
+    /// The amount of all periods is locked when the proposal is submitted.
+    /// The first period starts when the proposal is executed, the applicant claims the payments
+    /// of elapsed periods with `ClaimRecurringPayment`, the batch proposal
+    /// with `ProposalAction::CancelRecurringPayment` stops the payments.
     /// Requirements:
     /// * The proposal can be submitted only by the existing members or their delegate addresses
-    /// * The member can vote on the proposal only once
-    /// * Proposal must exist, the voting period must has started and not expired
+    /// * The applicant can't be the zero address
+    /// * The interval and the number of periods must be greater than zero
+    /// * The DAO must have enough funds to pay all periods
     /// Arguments:
-    /// * `proposal_id`: the proposal ID
-    /// * `vote`: the member  a member vote (YES or NO)
-    fn submit_vote(&mut self, proposal_id: u128, vote: Vote) {
+    /// * `applicant`: an actor that will be paid
+    /// * `amount`: the number of fungible tokens paid every period
+    /// * `interval`: the period length
+    /// * `periods`: the number of periods
+    /// * `quorum`: a certain threshold of YES votes in order for the proposal to pass
+    /// * `metadata`: the proposal title, summary, URI and content hash
+    async fn submit_recurring_proposal(
+        &mut self,
+        applicant: &ActorId,
+        amount: u128,
+        interval: u64,
+        periods: u32,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    ) {
         self.check_for_membership();
+        check_metadata(&metadata);
 
-        // checks that proposal exists, the voting period has started, not expired and that member did not vote on the proposal
-        let proposal = match self.proposals.get_mut(&proposal_id) {
-            Some(proposal) => {
-                if exec::block_timestamp() > proposal.starting_period + self.voting_period_length {
-                    panic!("proposal voting period has expired");
-                }
-                if exec::block_timestamp() < proposal.starting_period {
-                    panic!("voting period has not started");
-                }
-                if proposal
-                    .votes_by_member
-                    .iter()
-                    .any(|(actor, _vote)| msg::source().eq(actor))
-                {
-                    panic!("account has already voted on that proposal");
-                }
-                proposal
-            }
-            None => {
-                panic!("proposal does not exist");
-            }
-        };
+        if applicant.is_zero() {
+            panic!("Proposal for the zero address");
+        }
+        if interval == 0 || periods == 0 {
+            panic!("Invalid payment schedule");
+        }
+        let total = amount
+            .checked_mul(periods as u128)
+            .expect("Payment amount overflow");
 
-        let member = self.members.get_mut(&msg::source()).unwrap();
+        // check that DAO has sufficient funds
+        let balance = self.update_balance().await;
+        if balance.saturating_sub(self.locked_funds) < total {
+            panic!("Not enough funds in DAO");
+        }
 
-        match vote {
-            Vote::Yes => {
-                proposal.yes_votes = proposal.yes_votes.saturating_add(member.shares);
-                // it is necessary to save the highest id of the proposal - must be processed for member to ragequit
-                let id = member.highest_index_yes_vote.get_or_insert(proposal_id);
-                *id = proposal_id.max(*id);
-            }
-            Vote::No => {
-                proposal.no_votes = proposal.no_votes.saturating_add(member.shares);
-            }
+        let proposal_id = self.add_proposal(Proposal {
+            proposer: msg::source(),
+            applicant: *applicant,
+            quorum,
+            amount: total,
+            metadata,
+            kind: ProposalKind::Recurring {
+                amount,
+                interval,
+                periods,
+            },
+            ..Default::default()
+        });
+        self.locked_funds = self.locked_funds.saturating_add(total);
+
+        msg::reply(
+            DaoEvent::SubmitRecurringProposal {
+                proposer: msg::source(),
+                applicant: *applicant,
+                proposal_id,
+                amount: total,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    /// The text-only proposal that moves no funds
+    /// The result of the voting is recorded in the proposal,
+    /// the YES vote on it doesn't prevent the member from ragequitting.
+    /// Requirements:
+    /// * The proposal can be submitted only by the existing members or their delegate addresses
+    /// * The metadata must not exceed the length limits
+    /// Arguments:
+    /// * `metadata`: the proposal title, summary, URI and content hash
+    /// * `quorum`: a certain threshold of YES votes in order for the proposal to pass
+    fn submit_signal_proposal(&mut self, metadata: ProposalMetadata, quorum: u128) {
+        self.check_for_membership();
+        check_metadata(&metadata);
+
+        let proposal_id = self.add_proposal(Proposal {
+            proposer: msg::source(),
+            quorum,
+            metadata,
+            kind: ProposalKind::Signal,
+            ..Default::default()
+        });
+
+        msg::reply(
+            DaoEvent::SubmitSignalProposal {
+                proposer: msg::source(),
+                proposal_id,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    /// The funding proposal for routine payouts that passes without YES votes
+    /// The proposal is rejected only if NO votes exceed `objection_threshold` percent
+    /// of the voting power, members object with `SubmitVote`.
+    /// Requirements:
+    /// * The proposal can be submitted only by the existing members or their delegate addresses
+    /// * The receiver ID can't be the zero
+    /// * The total amount of the optimistic proposals that aren't processed yet
+    ///   can't exceed `optimistic_cap`
+    /// * The DAO must have enough funds to finance the proposal
+    /// * The metadata must not exceed `MAX_TITLE_LENGTH`, `MAX_SUMMARY_LENGTH` and `MAX_URI_LENGTH`
+    /// Arguments:
+    /// * `applicant`: an actor that will be funded
+    /// * `amount`: the number of fungible tokens that will be sent to the applicant
+    /// * `metadata`: the proposal title, summary, URI and content hash
+    async fn submit_optimistic_proposal(
+        &mut self,
+        applicant: &ActorId,
+        amount: u128,
+        metadata: ProposalMetadata,
+    ) {
+        self.check_for_membership();
+        check_metadata(&metadata);
+
+        if applicant.is_zero() {
+            panic!("Proposal for the zero address");
+        }
+        if self.optimistic_exposure.saturating_add(amount) > self.optimistic_cap {
+            panic!("Amount exceeds the optimistic proposal cap");
+        }
+
+        let balance = self.update_balance().await;
+        if balance.saturating_sub(self.locked_funds) < amount {
+            panic!("Not enough funds in DAO");
+        }
+
+        // the threshold is fixed when the proposal is submitted
+        let proposal_id = self.add_proposal(Proposal {
+            proposer: msg::source(),
+            applicant: *applicant,
+            amount,
+            metadata,
+            kind: ProposalKind::Optimistic {
+                objection_threshold: self.objection_threshold,
+            },
+            ..Default::default()
+        });
+        self.locked_funds = self.locked_funds.saturating_add(amount);
+        self.optimistic_exposure = self.optimistic_exposure.saturating_add(amount);
+
+        msg::reply(
+            DaoEvent::SubmitOptimisticProposal {
+                proposer: msg::source(),
+                applicant: *applicant,
+                proposal_id,
+                amount,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    // checks that the action of the proposal is valid and returns the number of tokens it transfers
+    fn check_action(&self, action: &ProposalAction) -> u128 {
+        match action {
+            ProposalAction::Transfer { recipient, amount } => {
+                if recipient.is_zero() {
+                    panic!("Proposal for the zero address");
+                }
+                return *amount;
+            }
+            ProposalAction::UpdateParameter(DaoParameter::DilutionBound(0)) => {
+                panic!("Dilution bound must be greater than zero");
+            }
+            ProposalAction::UpdateParameter(DaoParameter::PeriodDuration(0)) => {
+                panic!("Period duration must be greater than zero");
+            }
+            ProposalAction::UpdateParameter(DaoParameter::VotingPeriodLength(0)) => {
+                panic!("Voting period length must be greater than zero");
+            }
+            ProposalAction::UpdateParameter(DaoParameter::ObjectionThreshold(threshold))
+                if *threshold > 100 =>
+            {
+                panic!("Objection threshold can't exceed 100 percent");
+            }
+            ProposalAction::UpdateParameter(DaoParameter::Conviction(config))
+                if !config.is_valid() =>
+            {
+                panic!("Invalid conviction config");
+            }
+            ProposalAction::UpdateParameter(_) => {}
+            ProposalAction::Execute { destination, .. } => {
+                if destination.is_zero() {
+                    panic!("Proposal for the zero address");
+                }
+                if *destination == self.approved_token_program_id {
+                    panic!("Proposal can't send messages to the approved token program");
+                }
+            }
+            // the milestones are already funded by the grant
+            ProposalAction::ReleaseMilestone { proposal_id, index } => {
+                match self.proposals.get(proposal_id) {
+                    Some(Proposal {
+                        kind: ProposalKind::Milestones { milestones, .. },
+                        ..
+                    }) if (*index as usize) < milestones.len() => {}
+                    _ => panic!("Milestone does not exist"),
+                }
+            }
+            ProposalAction::CancelGrant { proposal_id } => {
+                if !matches!(
+                    self.proposals.get(proposal_id),
+                    Some(Proposal {
+                        kind: ProposalKind::Milestones { .. },
+                        ..
+                    })
+                ) {
+                    panic!("Grant does not exist");
+                }
+            }
+            ProposalAction::CancelStream { stream_id } => {
+                if !matches!(
+                    self.proposals.get(stream_id),
+                    Some(Proposal {
+                        kind: ProposalKind::Stream { .. },
+                        ..
+                    })
+                ) {
+                    panic!("Stream does not exist");
+                }
+            }
+            ProposalAction::CancelRecurringPayment { payment_id } => {
+                if !matches!(
+                    self.proposals.get(payment_id),
+                    Some(Proposal {
+                        kind: ProposalKind::Recurring { .. },
+                        ..
+                    })
+                ) {
+                    panic!("Recurring payment does not exist");
+                }
+            }
+        }
+        0
+    }
+
+    /// The proposal with several options, the action of the winning option is executed
+    /// The largest transfer among the options is locked when the proposal is submitted.
+    /// Members vote with `SubmitBallot`, the winner is decided by plurality or instant-runoff.
+    /// Requirements:
+    /// * The proposal can be submitted only by the existing members or their delegate addresses
+    /// * The proposal must contain at least two options
+    /// * The actions of the options must be valid, see `submit_batch_proposal`
+    /// * The DAO must have enough funds for the largest transfer among the options
+    /// Arguments:
+    /// * `options`: the options with their optional actions
+    /// * `method`: the method deciding the winning option
+    /// * `quorum`: a certain threshold of voted shares in order for the proposal to pass
+    /// * `metadata`: the proposal title, summary, URI and content hash
+    async fn submit_choice_proposal(
+        &mut self,
+        options: Vec<ChoiceOption>,
+        method: TallyMethod,
+        quorum: u128,
+        metadata: ProposalMetadata,
+    ) {
+        self.check_for_membership();
+        check_metadata(&metadata);
+
+        if options.len() < 2 {
+            panic!("Proposal must have at least two options");
+        }
+        let mut amount: u128 = 0;
+        for option in &options {
+            if option.label.len() > MAX_TITLE_LENGTH {
+                panic!("Option label exceeds {MAX_TITLE_LENGTH} bytes");
+            }
+            if let Some(action) = &option.action {
+                amount = amount.max(self.check_action(action));
+            }
+        }
+
+        // check that DAO has sufficient funds
+        let balance = self.update_balance().await;
+        if balance.saturating_sub(self.locked_funds) < amount {
+            panic!("Not enough funds in DAO");
+        }
+
+        let proposal_id = self.add_proposal(Proposal {
+            proposer: msg::source(),
+            amount,
+            quorum,
+            metadata,
+            kind: ProposalKind::Choice { options, method },
+            ..Default::default()
+        });
+        self.locked_funds = self.locked_funds.saturating_add(amount);
+
+        msg::reply(
+            DaoEvent::SubmitChoiceProposal {
+                proposer: msg::source(),
+                proposal_id,
+                amount,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    // sets the voting period of the new proposal and saves it
+    fn add_proposal(&mut self, mut proposal: Proposal) -> u128 {
+        let mut starting_period = exec::block_timestamp();
+        // compute startingPeriod for proposal
+        // there should be a minimum time interval between proposals (period_duration) so that members have time to ragequit
+        if self.proposal_id > 0 {
+            let previous_starting_period = self.proposals[&(self.proposal_id - 1)].starting_period;
+            if starting_period < previous_starting_period + self.period_duration {
+                starting_period = previous_starting_period + self.period_duration;
+            }
+        }
+        proposal.starting_period = starting_period;
+        proposal.ended_at = starting_period + self.voting_period_length;
+        // the change of the voting mode doesn't mix the vote weights of the open proposals
+        proposal.voting_mode = self.voting_mode;
+        // the votes are hidden until the voting period ends, the ballots of choice proposals are public
+        if self.reveal_period_length > 0 && !matches!(proposal.kind, ProposalKind::Choice { .. }) {
+            proposal.reveal_ended_at = Some(proposal.ended_at + self.reveal_period_length);
+        }
+
+        let proposal_id = self.proposal_id;
+        self.proposals.insert(proposal_id, proposal);
+        self.proposal_id = self.proposal_id.saturating_add(1);
+        proposal_id
+    }
+
+    /// The member submit his vote (YES or NO) on the proposal
+    /// The vote receipt with the weight, the time and the rationale of the vote is saved in the proposal.
+    /// Requirements:
+    /// * The proposal can be submitted only by the existing members or their delegate addresses
+    /// * The member can vote on the proposal only once
+    /// * Proposal must exist, the voting period must has started and not expired
+    /// * The reason must not exceed `MAX_REASON_LENGTH`
+    /// Arguments:
+    /// * `voter`: the member casting the vote
+    /// * `proposal_id`: the proposal ID
+    /// * `vote`: the member  a member vote (YES or NO)
+    /// * `rationale`: the optional reason of the vote or its hash
+    fn submit_vote(
+        &mut self,
+        voter: ActorId,
+        proposal_id: u128,
+        vote: Vote,
+        rationale: Option<VoteRationale>,
+    ) {
+        if !self.is_member(&voter) {
+            panic!("account is not a DAO member")
+        }
+        if let Some(VoteRationale::Reason(reason)) = &rationale {
+            if reason.len() > MAX_REASON_LENGTH {
+                panic!("Vote reason exceeds {MAX_REASON_LENGTH} bytes");
+            }
+        }
+
+        if let Err(error) = self.check_vote(&voter, proposal_id) {
+            panic!("{error}");
+        }
+
+        self.count_vote(voter, proposal_id, vote.clone(), rationale);
+
+        msg::reply(
+            DaoEvent::SubmitVote {
+                account: voter,
+                proposal_id,
+                vote,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    /// The member submits the votes on several proposals in one message
+    /// In the all-or-nothing mode no vote is counted if any of them is invalid,
+    /// otherwise the invalid votes are skipped and their errors are returned in the reply.
+    /// Requirements:
+    /// * The votes can be submitted only by the existing members or their delegate addresses
+    /// * The votes must not be empty
+    /// * Each vote must meet the requirements of `submit_vote` in the all-or-nothing mode
+    /// Arguments:
+    /// * `votes`: the proposal IDs with the member votes
+    /// * `all_or_nothing`: whether the message fails if any of the votes is invalid
+    fn submit_votes(&mut self, votes: Vec<(u128, Vote)>, all_or_nothing: bool) {
+        self.check_for_membership();
+        if votes.is_empty() {
+            panic!("No votes to submit");
+        }
+
+        // the votes are counted in the given order, so the repeated vote on the proposal is rejected
+        let mut results = Vec::with_capacity(votes.len());
+        for (proposal_id, vote) in votes {
+            let result = match self.check_vote(&msg::source(), proposal_id) {
+                Ok(()) => {
+                    self.count_vote(msg::source(), proposal_id, vote.clone(), None);
+                    VoteResult::Counted { vote }
+                }
+                Err(error) if all_or_nothing => {
+                    panic!("Vote on proposal {proposal_id} failed: {error}");
+                }
+                Err(error) => VoteResult::Rejected {
+                    error: String::from(error),
+                },
+            };
+            results.push((proposal_id, result));
+        }
+
+        msg::reply(
+            DaoEvent::SubmitVotes {
+                account: msg::source(),
+                results,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    // checks that proposal exists, the voting period has started, not expired and that member did not vote on the proposal
+    fn check_vote(&self, voter: &ActorId, proposal_id: u128) -> Result<(), &str> {
+        let Some(proposal) = self.proposals.get(&proposal_id) else {
+            return Err("proposal does not exist");
+        };
+        if exec::block_timestamp() > proposal.starting_period + self.voting_period_length {
+            return Err("proposal voting period has expired");
+        }
+        if exec::block_timestamp() < proposal.starting_period {
+            return Err("voting period has not started");
+        }
+        if matches!(proposal.kind, ProposalKind::Choice { .. }) {
+            return Err("Choice proposals are voted with ballots");
+        }
+        if proposal.reveal_ended_at.is_some() {
+            return Err("Votes on the proposal must be committed");
+        }
+        if self.has_voted(proposal_id, voter) {
+            return Err("account has already voted on that proposal");
+        }
+        Ok(())
+    }
+
+    /// The member commits to the hidden vote on the commit-reveal proposal
+    /// The proposal is submitted in the commit-reveal mode when `reveal_period_length` isn't zero,
+    /// the commitment is revealed with `RevealVote` after the voting period.
+    /// Requirements:
+    /// * The commitment can be submitted only by the existing members or their delegate addresses
+    /// * The member can commit to the vote on the proposal only once
+    /// * Proposal must exist and be a commit-reveal proposal, the voting period must has started and not expired
+    /// Arguments:
+    /// * `proposal_id`: the proposal ID
+    /// * `commitment`: the hash of the vote, see `vote_commitment`
+    fn commit_vote(&mut self, proposal_id: u128, commitment: [u8; 32]) {
+        self.check_for_membership();
+
+        match self.proposals.get(&proposal_id) {
+            Some(proposal) => {
+                if exec::block_timestamp() > proposal.starting_period + self.voting_period_length {
+                    panic!("proposal voting period has expired");
+                }
+                if exec::block_timestamp() < proposal.starting_period {
+                    panic!("voting period has not started");
+                }
+                if proposal.reveal_ended_at.is_none() {
+                    panic!("Proposal is not a commit-reveal proposal");
+                }
+            }
+            None => {
+                panic!("proposal does not exist");
+            }
+        }
+        let commitments = self.vote_commitments.entry(proposal_id).or_default();
+        if commitments.contains_key(&msg::source()) {
+            panic!("account has already voted on that proposal");
+        }
+        commitments.insert(msg::source(), commitment);
+
+        msg::reply(
+            DaoEvent::CommitVote {
+                account: msg::source(),
+                proposal_id,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    /// The member reveals the committed vote, the revealed vote is counted with the current member shares
+    /// The commitments that aren't revealed before the reveal period ends are ignored.
+    /// Requirements:
+    /// * The vote can be revealed only by the existing members or their delegate addresses
+    /// * Proposal must exist, the voting period must has expired and the reveal period must not expire
+    /// * The vote and the salt must match the commitment of the member
+    /// * The member can reveal the vote only once
+    /// Arguments:
+    /// * `proposal_id`: the proposal ID
+    /// * `vote`: the committed vote
+    /// * `salt`: the salt of the commitment
+    fn reveal_vote(&mut self, proposal_id: u128, vote: Vote, salt: [u8; 32]) {
+        self.check_for_membership();
+
+        match self.proposals.get(&proposal_id) {
+            Some(proposal) => {
+                let Some(reveal_ended_at) = proposal.reveal_ended_at else {
+                    panic!("Proposal is not a commit-reveal proposal");
+                };
+                if exec::block_timestamp() <= proposal.starting_period + self.voting_period_length {
+                    panic!("voting period has not expired");
+                }
+                if exec::block_timestamp() > reveal_ended_at {
+                    panic!("reveal period has expired");
+                }
+                let commitment = vote_commitment(proposal_id, &msg::source(), &vote, &salt);
+                let committed = self
+                    .vote_commitments
+                    .get(&proposal_id)
+                    .and_then(|commitments| commitments.get(&msg::source()));
+                if committed != Some(&commitment) {
+                    panic!("Vote does not match the commitment");
+                }
+                if self.has_voted(proposal_id, &msg::source()) {
+                    panic!("account has already revealed the vote");
+                }
+            }
+            None => {
+                panic!("proposal does not exist");
+            }
+        }
+
+        self.count_vote(msg::source(), proposal_id, vote.clone(), None);
+
+        msg::reply(
+            DaoEvent::SubmitVote {
+                account: msg::source(),
+                proposal_id,
+                vote,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    // adds the vote weight of the member to the tally of the proposal and saves the vote receipt
+    fn count_vote(
+        &mut self,
+        voter: ActorId,
+        proposal_id: u128,
+        vote: Vote,
+        rationale: Option<VoteRationale>,
+    ) {
+        let proposal = self.proposals.get_mut(&proposal_id).unwrap();
+        let member = self.members.get_mut(&voter).unwrap();
+        let weight = vote_weight(member.shares, proposal.voting_mode);
+
+        match vote {
+            Vote::Yes => {
+                proposal.yes_votes = proposal.yes_votes.saturating_add(weight);
+                // it is necessary to save the highest id of the proposal - must be processed for member to ragequit
+                // signal proposals move no funds, so members can leave before they are processed
+                if !matches!(proposal.kind, ProposalKind::Signal) {
+                    let id = member.highest_index_yes_vote.get_or_insert(proposal_id);
+                    *id = proposal_id.max(*id);
+                }
+                // remember the largest DAO size seen by a YES vote to detect dilution on processing
+                proposal.max_total_shares_at_yes_vote =
+                    proposal.max_total_shares_at_yes_vote.max(self.total_shares);
+            }
+            Vote::No => {
+                proposal.no_votes = proposal.no_votes.saturating_add(weight);
+            }
+        }
+        let votes = self.votes.entry(proposal_id).or_default();
+        let sequence = votes.len() as u64;
+        votes.insert(
+            voter,
+            VoteReceipt {
+                vote,
+                weight,
+                timestamp: exec::block_timestamp(),
+                rationale,
+                sequence,
+            },
+        );
+    }
+
+    // checks whether the member has voted on the proposal
+    fn has_voted(&self, proposal_id: u128, voter: &ActorId) -> bool {
+        self.votes
+            .get(&proposal_id)
+            .is_some_and(|votes| votes.contains_key(voter))
+    }
+
+    /// Submits the vote signed by the member, so that a relayer can pay for the message
+    /// The member signs `vote_signing_payload` with the sr25519 key in `SR25519_SIGNING_CONTEXT`
+    /// or with the ed25519 key, the voter ID is the public key of the member.
+    /// Requirements:
+    /// * `nonce` must equal the current nonce of the voter, it is incremented by the vote
+    /// * The signature must be made by the voter over the payload of this DAO
+    /// * The vote must meet the requirements of `submit_vote`
+    /// Arguments:
+    /// * `proposal_id`: the proposal ID
+    /// * `vote`: the member vote (YES or NO)
+    /// * `voter`: the member who signed the vote
+    /// * `nonce`: the nonce of the voter preventing the replay of the signed vote
+    /// * `signature`: the signature of the vote
+    fn submit_vote_by_sig(
+        &mut self,
+        proposal_id: u128,
+        vote: Vote,
+        voter: ActorId,
+        nonce: u64,
+        signature: VoteSignature,
+    ) {
+        let expected_nonce = self.nonces.get(&voter).copied().unwrap_or_default();
+        if nonce != expected_nonce {
+            panic!("Invalid nonce, expected {expected_nonce}");
+        }
+        let payload = vote_signing_payload(exec::program_id(), proposal_id, &vote, nonce);
+        if !verify_signature(&voter, &payload, &signature) {
+            panic!("Invalid signature");
+        }
+        self.nonces.insert(voter, nonce.saturating_add(1));
+        self.submit_vote(voter, proposal_id, vote, None);
+    }
+
+    /// The member submits the ballot on the choice proposal
+    /// Requirements:
+    /// * The ballot can be submitted only by the existing members or their delegate addresses
+    /// * The member can vote on the proposal only once
+    /// * Proposal must exist and be a choice proposal, the voting period must has started and not expired
+    /// * The ballot must match the tally method of the proposal and refer to existing options,
+    ///   the split weights can't exceed the member shares
+    /// * The vote weight of the split shares is shared out across options in proportion to them
+    /// Arguments:
+    /// * `proposal_id`: the proposal ID
+    /// * `ballot`: the member ballot
+    fn submit_ballot(&mut self, proposal_id: u128, ballot: Ballot) {
+        self.check_for_membership();
+
+        let proposal = match self.proposals.get_mut(&proposal_id) {
+            Some(proposal) => {
+                if exec::block_timestamp() > proposal.starting_period + self.voting_period_length {
+                    panic!("proposal voting period has expired");
+                }
+                if exec::block_timestamp() < proposal.starting_period {
+                    panic!("voting period has not started");
+                }
+                if self
+                    .ballot_receipts
+                    .get(&proposal_id)
+                    .is_some_and(|ballots| ballots.contains_key(&msg::source()))
+                {
+                    panic!("account has already voted on that proposal");
+                }
+                proposal
+            }
+            None => {
+                panic!("proposal does not exist");
+            }
+        };
+        let ProposalKind::Choice { options, method } = &proposal.kind else {
+            panic!("Proposal is not a choice proposal");
+        };
+        let member = self.members.get_mut(&msg::source()).unwrap();
+        let voting_mode = proposal.voting_mode;
+
+        let is_option = |option: &u32| (*option as usize) < options.len();
+        let (weight, ballot) = match (method, ballot) {
+            (TallyMethod::Plurality, Ballot::Single(option)) if is_option(&option) => (
+                vote_weight(member.shares, voting_mode),
+                Ballot::Single(option),
+            ),
+            (TallyMethod::Plurality, Ballot::Split(split))
+                if !split.is_empty()
+                    && split.iter().all(|(option, _)| is_option(option))
+                    && !has_duplicates(split.iter().map(|(option, _)| *option)) =>
+            {
+                let shares = split
+                    .iter()
+                    .fold(0u128, |total, (_, shares)| total.saturating_add(*shares));
+                if shares > member.shares {
+                    panic!("Ballot weight exceeds the member shares");
+                }
+                // the weight of all split shares is shared out across options in proportion,
+                // so splitting a ballot doesn't add weight in the quadratic mode
+                let total_weight = vote_weight(shares, voting_mode);
+                let split: Vec<(u32, u128)> = split
+                    .into_iter()
+                    .map(|(option, option_shares)| {
+                        let weight = if total_weight == shares {
+                            option_shares
+                        } else {
+                            total_weight.saturating_mul(option_shares) / shares
+                        };
+                        (option, weight)
+                    })
+                    .collect();
+                let weight = split
+                    .iter()
+                    .fold(0u128, |total, (_, weight)| total.saturating_add(*weight));
+                (weight, Ballot::Split(split))
+            }
+            (TallyMethod::InstantRunoff, Ballot::Ranked(ranking))
+                if !ranking.is_empty()
+                    && ranking.iter().all(is_option)
+                    && !has_duplicates(ranking.iter().copied()) =>
+            {
+                (
+                    vote_weight(member.shares, voting_mode),
+                    Ballot::Ranked(ranking),
+                )
+            }
+            _ => panic!("Invalid ballot"),
+        };
+
+        // the winning option can move funds, so the ballot locks the shares like a YES vote
+        let id = member.highest_index_yes_vote.get_or_insert(proposal_id);
+        *id = proposal_id.max(*id);
+        proposal.max_total_shares_at_yes_vote =
+            proposal.max_total_shares_at_yes_vote.max(self.total_shares);
+        let ballots = self.ballot_receipts.entry(proposal_id).or_default();
+        let sequence = ballots.len() as u64;
+        ballots.insert(
+            msg::source(),
+            BallotReceipt {
+                ballot,
+                weight,
+                timestamp: exec::block_timestamp(),
+                sequence,
+            },
+        );
+
+        msg::reply(
+            DaoEvent::SubmitBallot {
+                account: msg::source(),
+                proposal_id,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    /// The proposal processing after the proposal completes during the grace period.
+    /// If the proposal is accepted, it is queued for the execution after the timelock.
+    /// Requirements:
+    /// * The previous proposal must be processed
+    /// * The proposal must exist and be ready for processing
+    /// * The proposal must not be already be processed
+    /// * The proposal fails automatically if `total_shares` fell below
+    ///   `max_total_shares_at_yes_vote / dilution_bound` (e.g. because of mass ragequit)
+    /// Arguments:
+    /// * `proposal_id`: the proposal ID
+    fn process_proposal(&mut self, proposal_id: u128) {
+        if proposal_id > 0 && !self.proposals.get(&(&proposal_id - 1)).unwrap().processed {
+            panic!("Previous proposal must be processed");
+        }
+        let voting_power = match self.proposals.get(&proposal_id) {
+            Some(proposal) => self.voting_power(proposal.voting_mode),
+            None => 0,
+        };
+        let proposal = match self.proposals.get_mut(&proposal_id) {
+            Some(proposal) => {
+                if proposal.processed {
+                    panic!("Proposal has already been processed");
+                }
+                // the grace period of commit-reveal proposals starts when the reveal period ends
+                let voting_ended_at = proposal
+                    .reveal_ended_at
+                    .unwrap_or(proposal.starting_period + self.voting_period_length);
+                if exec::block_timestamp() < voting_ended_at + self.grace_period_length {
+                    panic!("Proposal is not ready to be processed");
+                }
+                proposal
+            }
+            None => {
+                panic!("proposal does not exist");
+            }
+        };
+
+        proposal.processed = true;
+        // the proposal fails if too many members ragequit after YES votes were cast
+        // or if no voting power is left at all
+        let failed = self.total_shares.saturating_mul(self.dilution_bound)
+            < proposal.max_total_shares_at_yes_vote
+            || voting_power == 0;
+        proposal.did_pass = if let ProposalKind::Choice { options, method } = &proposal.kind {
+            let ballots: Vec<&BallotReceipt> = self
+                .ballot_receipts
+                .get(&proposal_id)
+                .map(|ballots| ballots.values().collect())
+                .unwrap_or_default();
+            proposal.winning_option = match method {
+                TallyMethod::Plurality => plurality_winner(options.len(), &ballots),
+                TallyMethod::InstantRunoff => instant_runoff_winner(options.len(), &ballots),
+            };
+            let turnout = ballots.iter().fold(0u128, |turnout, receipt| {
+                turnout.saturating_add(receipt.weight)
+            });
+            !failed
+                && proposal.winning_option.is_some()
+                && turnout * 10_000 / voting_power >= proposal.quorum * 100
+        } else if let ProposalKind::Optimistic {
+            objection_threshold,
+        } = proposal.kind
+        {
+            // the decided proposal doesn't count towards the cap anymore
+            self.optimistic_exposure = self.optimistic_exposure.saturating_sub(proposal.amount);
+            !failed && proposal.no_votes * 10_000 / voting_power <= objection_threshold * 100
+        } else {
+            !failed
+                && proposal.yes_votes > proposal.no_votes
+                && proposal.yes_votes * 10_000 / voting_power >= proposal.quorum * 100
+        };
+
+        if proposal.did_pass && matches!(proposal.kind, ProposalKind::Choice { .. }) {
+            // only the transfer of the winning option stays reserved
+            let reserved = match proposal.actions() {
+                [ProposalAction::Transfer { amount, .. }] => *amount,
+                _ => 0,
+            };
+            self.locked_funds = self
+                .locked_funds
+                .saturating_sub(proposal.amount.saturating_sub(reserved));
+            proposal.amount = reserved;
+            proposal.action_statuses = vec![ActionStatus::Pending; proposal.actions().len()];
+        }
+
+        if proposal.did_pass && matches!(proposal.kind, ProposalKind::Signal) {
+            // there is nothing to execute
+            proposal.status = ProposalStatus::Executed;
+        } else if proposal.did_pass {
+            // members have time to ragequit before the proposal is executed
+            proposal.status = ProposalStatus::Queued;
+            proposal.eta = exec::block_timestamp() + self.timelock;
+        } else {
+            proposal.status = ProposalStatus::Rejected;
+            self.locked_funds = self.locked_funds.saturating_sub(proposal.amount);
+        }
+
+        msg::reply(
+            DaoEvent::ProcessProposal {
+                applicant: proposal.applicant,
+                proposal_id,
+                did_pass: proposal.did_pass,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    /// Executes the queued proposal after its timelock has expired.
+    /// The indicated amount of tokens are sent to the applicant,
+    /// for the execution proposal the message is sent and its reply is recorded,
+    /// the actions of the batch proposal are executed one by one.
+    /// If the token transfer of the funding proposal fails, the proposal stays queued
+    /// and can be executed again.
+    /// Requirements:
+    /// * The proposal must exist and be queued
+    /// * The timelock of the proposal must expire
+    /// * No other token transaction of DAO must be in progress for the funding proposal
+    /// Arguments:
+    /// * `proposal_id`: the proposal ID
+    async fn execute_proposal(&mut self, proposal_id: u128) {
+        let balance = self.update_balance().await;
+        let proposal = match self.proposals.get_mut(&proposal_id) {
+            Some(proposal) => {
+                if !matches!(proposal.status, ProposalStatus::Queued) {
+                    panic!("Proposal is not queued");
+                }
+                if exec::block_timestamp() < proposal.eta {
+                    panic!("Proposal timelock has not expired");
+                }
+                if matches!(
+                    proposal.kind,
+                    ProposalKind::Funding | ProposalKind::Optimistic { .. }
+                ) && !self.transactions.is_empty()
+                {
+                    panic!("Another token transaction is in progress");
+                }
+                proposal
+            }
+            None => {
+                panic!("proposal does not exist");
+            }
+        };
+        proposal.status = ProposalStatus::Executed;
+        let (applicant, amount) = (proposal.applicant, proposal.amount);
+
+        match proposal.kind.clone() {
+            ProposalKind::Funding | ProposalKind::Optimistic { .. } => {
+                let tx_id = self.start_transaction(
+                    TransactionKind::ExecuteProposal { proposal_id },
+                    exec::program_id(),
+                    applicant,
+                    amount,
+                    balance,
+                );
+                self.execute_transaction(tx_id).await;
+            }
+            ProposalKind::Execute {
+                destination,
+                payload,
+                value,
+                gas_limit,
+            } => {
+                let execution = execute_message(destination, payload, value, gas_limit).await;
+                if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+                    proposal.execution = Some(execution);
+                }
+                msg::reply(DaoEvent::ExecuteProposal { proposal_id }, 0).unwrap();
+            }
+            ProposalKind::Batch { .. } | ProposalKind::Choice { .. } => {
+                self.execute_actions(proposal_id).await;
+            }
+            // the grant stays locked until its milestones are released or cancelled
+            ProposalKind::Milestones { .. } => {
+                msg::reply(DaoEvent::ExecuteProposal { proposal_id }, 0).unwrap();
+            }
+            ProposalKind::Stream { duration, cliff } => {
+                self.streams.insert(
+                    proposal_id,
+                    Stream {
+                        recipient: applicant,
+                        amount,
+                        claimed: 0,
+                        start: exec::block_timestamp(),
+                        duration,
+                        cliff,
+                        cancelled_at: None,
+                    },
+                );
+                msg::reply(DaoEvent::ExecuteProposal { proposal_id }, 0).unwrap();
+            }
+            ProposalKind::Signal => {
+                msg::reply(DaoEvent::ExecuteProposal { proposal_id }, 0).unwrap();
+            }
+            ProposalKind::Recurring {
+                amount,
+                interval,
+                periods,
+            } => {
+                self.recurring_payments.insert(
+                    proposal_id,
+                    RecurringPayment {
+                        recipient: applicant,
+                        amount,
+                        interval,
+                        periods,
+                        paid_periods: 0,
+                        start: exec::block_timestamp(),
+                        cancelled_at: None,
+                    },
+                );
+                msg::reply(DaoEvent::ExecuteProposal { proposal_id }, 0).unwrap();
+            }
+        }
+    }
+
+    /// Sends the vested tokens of the stream to its recipient
+    /// Requirements:
+    /// * No other token transaction of DAO must be in progress
+    /// * The stream must exist
+    /// * `msg::source()` must be the recipient of the stream
+    /// * The stream must have vested tokens that aren't claimed yet
+    /// Arguments:
+    /// * `stream_id`: the stream ID
+    async fn claim_stream(&mut self, stream_id: u128) {
+        let balance = self.update_balance().await;
+        self.check_no_pending_transactions();
+        let stream = self.streams.get(&stream_id).expect("Stream does not exist");
+        if stream.recipient != msg::source() {
+            panic!("Only the recipient can claim the stream");
+        }
+        let amount = stream.claimable(exec::block_timestamp());
+        if amount == 0 {
+            panic!("Nothing to claim");
+        }
+        let tx_id = self.start_transaction(
+            TransactionKind::Stream { stream_id },
+            exec::program_id(),
+            stream.recipient,
+            amount,
+            balance,
+        );
+        self.execute_transaction(tx_id).await;
+    }
+
+    /// Sends the payments of the elapsed periods to the recipient
+    /// Requirements:
+    /// * No other token transaction of DAO must be in progress
+    /// * The recurring payment must exist
+    /// * `msg::source()` must be the recipient of the payment
+    /// * At least one elapsed period must be unpaid
+    /// Arguments:
+    /// * `payment_id`: the recurring payment ID
+    async fn claim_recurring_payment(&mut self, payment_id: u128) {
+        let balance = self.update_balance().await;
+        self.check_no_pending_transactions();
+        let payment = self
+            .recurring_payments
+            .get(&payment_id)
+            .expect("Recurring payment does not exist");
+        if payment.recipient != msg::source() {
+            panic!("Only the recipient can claim the payment");
+        }
+        let periods = payment.unpaid_periods(exec::block_timestamp());
+        if periods == 0 {
+            panic!("Nothing to claim");
+        }
+        let tx_id = self.start_transaction(
+            TransactionKind::RecurringPayment {
+                payment_id,
+                periods,
+            },
+            exec::program_id(),
+            payment.recipient,
+            payment.amount.saturating_mul(periods as u128),
+            balance,
+        );
+        self.execute_transaction(tx_id).await;
+    }
+
+    // stops the recurring payment and returns the tokens of the remaining periods to the treasury,
+    // the elapsed periods can still be claimed
+    fn cancel_recurring_payment(&mut self, payment_id: u128) -> Result<(), &str> {
+        // the paid periods must be known
+        if !self.transactions.is_empty() {
+            return Err("Another token transaction is in progress");
+        }
+        let payment = self
+            .recurring_payments
+            .get_mut(&payment_id)
+            .ok_or("Recurring payment does not exist")?;
+        if payment.cancelled_at.is_some() {
+            return Err("Recurring payment is already cancelled");
+        }
+        let timestamp = exec::block_timestamp();
+        payment.cancelled_at = Some(timestamp);
+        let remaining = payment.periods - payment.due_periods(timestamp);
+        self.locked_funds = self
+            .locked_funds
+            .saturating_sub(payment.amount.saturating_mul(remaining as u128));
+        Ok(())
+    }
+
+    // stops the vesting of the stream and returns the unvested tokens to the treasury,
+    // the vested tokens can still be claimed
+    fn cancel_stream(&mut self, stream_id: u128) -> Result<(), &str> {
+        // the claimed amount must be known
+        if !self.transactions.is_empty() {
+            return Err("Another token transaction is in progress");
+        }
+        let stream = self
+            .streams
+            .get_mut(&stream_id)
+            .ok_or("Stream does not exist")?;
+        if stream.cancelled_at.is_some() {
+            return Err("Stream is already cancelled");
+        }
+        let timestamp = exec::block_timestamp();
+        stream.cancelled_at = Some(timestamp);
+        let unvested = stream.amount.saturating_sub(stream.vested(timestamp));
+        self.locked_funds = self.locked_funds.saturating_sub(unvested);
+        Ok(())
+    }
+
+    /// Releases the milestone of the executed grant proposal to the applicant
+    /// Requirements:
+    /// * No other token transaction of DAO must be in progress
+    /// * `msg::source()` must be the reviewer of the grant
+    /// * The grant proposal must be executed and the milestone must not be released or cancelled
+    /// Arguments:
+    /// * `proposal_id`: the grant proposal ID
+    /// * `index`: the milestone index
+    async fn release_milestone(&mut self, proposal_id: u128, index: u32) {
+        let balance = self.update_balance().await;
+        self.check_no_pending_transactions();
+        match self.proposals.get(&proposal_id) {
+            Some(Proposal {
+                kind: ProposalKind::Milestones { reviewer, .. },
+                ..
+            }) if !reviewer.is_zero() && *reviewer == msg::source() => {}
+            Some(_) => {
+                panic!("Only the reviewer can release milestones");
+            }
+            None => {
+                panic!("proposal does not exist");
+            }
+        }
+        let (applicant, amount) = self
+            .milestone_payout(proposal_id, index)
+            .unwrap_or_else(|error| panic!("{error}"));
+        let tx_id = self.start_transaction(
+            TransactionKind::Milestone { proposal_id, index },
+            exec::program_id(),
+            applicant,
+            amount,
+            balance,
+        );
+        self.execute_transaction(tx_id).await;
+    }
+
+    // returns the applicant and the amount of the milestone that can be released
+    fn milestone_payout(&self, proposal_id: u128, index: u32) -> Result<(ActorId, u128), &str> {
+        let proposal = self
+            .proposals
+            .get(&proposal_id)
+            .ok_or("Grant does not exist")?;
+        let ProposalKind::Milestones { milestones, .. } = &proposal.kind else {
+            return Err("Grant does not exist");
+        };
+        if !matches!(proposal.status, ProposalStatus::Executed) {
+            return Err("Grant is not executed");
+        }
+        match (
+            milestones.get(index as usize),
+            proposal.milestone_statuses.get(index as usize),
+        ) {
+            (Some(milestone), Some(MilestoneStatus::Pending)) => {
+                Ok((proposal.applicant, milestone.amount))
+            }
+            (Some(_), Some(_)) => Err("Milestone is already settled"),
+            _ => Err("Milestone does not exist"),
+        }
+    }
+
+    // cancels unreleased milestones of the grant and returns their tokens to the treasury
+    fn cancel_grant(&mut self, proposal_id: u128) -> Result<(), &str> {
+        // the milestone being transferred can't be cancelled
+        if !self.transactions.is_empty() {
+            return Err("Another token transaction is in progress");
+        }
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .ok_or("Grant does not exist")?;
+        let ProposalKind::Milestones { milestones, .. } = &proposal.kind else {
+            return Err("Grant does not exist");
+        };
+        if !matches!(proposal.status, ProposalStatus::Executed) {
+            return Err("Grant is not executed");
+        }
+        let mut unreleased: u128 = 0;
+        for (milestone, status) in milestones
+            .iter()
+            .zip(proposal.milestone_statuses.iter_mut())
+        {
+            if matches!(status, MilestoneStatus::Pending) {
+                *status = MilestoneStatus::Cancelled;
+                unreleased = unreleased.saturating_add(milestone.amount);
+            }
+        }
+        self.locked_funds = self.locked_funds.saturating_sub(unreleased);
+        Ok(())
+    }
+
+    // marks the milestone released by the batch proposal action
+    fn mark_milestone_released(&mut self, proposal_id: u128, index: u32) {
+        let action = self
+            .proposals
+            .get(&proposal_id)
+            .and_then(|proposal| proposal.actions().get(index as usize).cloned());
+        if let Some(ProposalAction::ReleaseMilestone { proposal_id, index }) = action {
+            if let Some(status) = self
+                .proposals
+                .get_mut(&proposal_id)
+                .and_then(|grant| grant.milestone_statuses.get_mut(index as usize))
+            {
+                *status = MilestoneStatus::Released;
+            }
+        }
+    }
+
+    /// Cancels the queued proposal before its execution
+    /// Requirements:
+    /// * `msg::source()` must be a guardian
+    /// * The proposal must exist and be queued
+    /// * The timelock of the proposal must not expire
+    /// Arguments:
+    /// * `proposal_id`: the proposal ID
+    fn veto_proposal(&mut self, proposal_id: u128) {
+        match self.proposals.get(&proposal_id) {
+            Some(proposal) => {
+                if !matches!(proposal.status, ProposalStatus::Queued) {
+                    panic!("Proposal is not queued");
+                }
+                if exec::block_timestamp() >= proposal.eta {
+                    panic!("Proposal timelock has expired");
+                }
+            }
+            None => {
+                panic!("proposal does not exist");
+            }
+        };
+        if !self.approve_guardian_action(GuardianAction::Veto { proposal_id }) {
+            return;
+        }
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .expect("Proposal does not exist");
+        proposal.status = ProposalStatus::Vetoed;
+        self.locked_funds = self.locked_funds.saturating_sub(proposal.amount);
+
+        msg::reply(DaoEvent::VetoProposal { proposal_id }, 0).unwrap();
+    }
+
+    /// Pauses the indicated actions
+    /// Requirements:
+    /// * `msg::source()` must be a guardian
+    /// Arguments:
+    /// * `actions`: the actions to pause
+    fn pause(&mut self, actions: Vec<PausableAction>) {
+        let actions = canonical_actions(actions);
+        if !self.approve_guardian_action(GuardianAction::Pause(actions.clone())) {
+            return;
+        }
+        self.paused.extend(actions.iter().copied());
+        msg::reply(DaoEvent::Pause { actions }, 0).unwrap();
+    }
+
+    /// Unpauses the indicated actions
+    /// Requirements:
+    /// * `msg::source()` must be a guardian
+    /// Arguments:
+    /// * `actions`: the actions to unpause
+    fn unpause(&mut self, actions: Vec<PausableAction>) {
+        let actions = canonical_actions(actions);
+        if !self.approve_guardian_action(GuardianAction::Unpause(actions.clone())) {
+            return;
+        }
+        for action in &actions {
+            self.paused.remove(action);
+        }
+        msg::reply(DaoEvent::Unpause { actions }, 0).unwrap();
+    }
+
+    /// Withdraws the approval of the guardian action that hasn't been performed yet,
+    /// so stale approvals don't count towards the action later
+    /// Requirements:
+    /// * `msg::source()` must be a guardian that approved the action
+    /// Arguments:
+    /// * `action`: the approved guardian action
+    fn revoke_guardian_approval(&mut self, action: GuardianAction) {
+        let guardian = msg::source();
+        if !self.guardians.contains(&guardian) {
+            panic!("Only guardians can perform this action");
+        }
+        let action = match action {
+            GuardianAction::Pause(actions) => GuardianAction::Pause(canonical_actions(actions)),
+            GuardianAction::Unpause(actions) => GuardianAction::Unpause(canonical_actions(actions)),
+            action => action,
+        };
+        let approvals = match self.guardian_approvals.get_mut(&action) {
+            Some(approvals) if approvals.contains(&guardian) => {
+                approvals.remove(&guardian);
+                approvals.len() as u32
+            }
+            _ => panic!("The guardian hasn't approved the action"),
+        };
+        if approvals == 0 {
+            self.guardian_approvals.remove(&action);
+        }
+        msg::reply(
+            DaoEvent::RevokeGuardianApproval {
+                guardian,
+                action,
+                approvals,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    // records the approval of `msg::source()` and returns true once the action
+    // is approved by `guardian_threshold` guardians,
+    // otherwise replies with the current number of approvals
+    fn approve_guardian_action(&mut self, action: GuardianAction) -> bool {
+        let guardian = msg::source();
+        if !self.guardians.contains(&guardian) {
+            panic!("Only guardians can perform this action");
+        }
+        let approvals = self.guardian_approvals.entry(action.clone()).or_default();
+        approvals.insert(guardian);
+        let count = approvals.len() as u32;
+        if count < self.guardian_threshold {
+            msg::reply(
+                DaoEvent::GuardianApproval {
+                    guardian,
+                    action,
+                    approvals: count,
+                },
+                0,
+            )
+            .unwrap();
+            return false;
+        }
+        self.guardian_approvals.remove(&action);
+        true
+    }
+
+    fn check_not_paused(&self, action: &DaoAction) {
+        let action = match action {
+            DaoAction::Deposit { .. } => PausableAction::Deposit,
+            DaoAction::SubmitFundingProposal { .. } => PausableAction::SubmitFundingProposal,
+            DaoAction::SubmitExecuteProposal { .. } => PausableAction::SubmitExecuteProposal,
+            DaoAction::SubmitBatchProposal { .. } => PausableAction::SubmitBatchProposal,
+            DaoAction::SubmitMilestoneProposal { .. } => PausableAction::SubmitMilestoneProposal,
+            DaoAction::SubmitStreamProposal { .. } => PausableAction::SubmitStreamProposal,
+            DaoAction::SubmitRecurringProposal { .. } => PausableAction::SubmitRecurringProposal,
+            DaoAction::SubmitSignalProposal { .. } => PausableAction::SubmitSignalProposal,
+            DaoAction::SubmitOptimisticProposal { .. } => PausableAction::SubmitOptimisticProposal,
+            DaoAction::ExecuteProposal { .. } => PausableAction::ExecuteProposal,
+            DaoAction::SubmitChoiceProposal { .. } => PausableAction::SubmitChoiceProposal,
+            DaoAction::SubmitVote { .. }
+            | DaoAction::SubmitVoteWithRationale { .. }
+            | DaoAction::SubmitVoteBySig { .. }
+            | DaoAction::SubmitVotes { .. }
+            | DaoAction::CommitVote { .. }
+            | DaoAction::RevealVote { .. } => PausableAction::SubmitVote,
+            DaoAction::SubmitBallot { .. } => PausableAction::SubmitBallot,
+            DaoAction::ResumeExecution { .. } | DaoAction::CancelExecution { .. } => {
+                PausableAction::ResumeExecution
+            }
+            DaoAction::ReleaseMilestone { .. } => PausableAction::ReleaseMilestone,
+            DaoAction::ClaimStream { .. } => PausableAction::ClaimStream,
+            DaoAction::ClaimRecurringPayment { .. } => PausableAction::ClaimRecurringPayment,
+            DaoAction::SubmitConvictionProposal { .. } => PausableAction::SubmitConvictionProposal,
+            DaoAction::StakeConviction { .. } => PausableAction::StakeConviction,
+            DaoAction::ExecuteConvictionProposal { .. } => {
+                PausableAction::ExecuteConvictionProposal
+            }
+            // guardian actions, exits, pending transfers and state queries are never paused
+            DaoAction::VetoProposal { .. }
+            | DaoAction::Pause { .. }
+            | DaoAction::Unpause { .. }
+            | DaoAction::RevokeGuardianApproval { .. }
+            | DaoAction::RageQuit { .. }
+            | DaoAction::ClaimExit { .. }
+            | DaoAction::ProcessProposal { .. }
+            | DaoAction::WithdrawConviction { .. }
+            | DaoAction::ContinueTransaction { .. }
+            | DaoAction::SettleTransaction { .. }
+            | DaoAction::QueryState { .. } => return,
+        };
+        if self.paused.contains(&action) {
+            panic!("Action is paused");
+        }
+    }
+
+    // executes the actions of the passed batch proposal one by one starting from `next_action`
+    async fn execute_actions(&mut self, proposal_id: u128) {
+        loop {
+            let proposal = self
+                .proposals
+                .get(&proposal_id)
+                .expect("Proposal does not exist");
+            let halt_on_failure = matches!(
+                proposal.kind,
+                ProposalKind::Batch {
+                    halt_on_failure: true,
+                    ..
+                }
+            );
+            let index = proposal.next_action as usize;
+            let Some(action) = proposal.actions().get(index).cloned() else {
+                // all actions have been executed
+                msg::reply(DaoEvent::ExecuteProposal { proposal_id }, 0).unwrap();
+                return;
+            };
+
+            if matches!(proposal.action_statuses[index], ActionStatus::Pending)
+                && !self
+                    .execute_action(proposal_id, index, action.clone())
+                    .await
+            {
+                // the transfer has been settled by `ContinueTransaction` that resumes the execution
+                // or the execution waits for another token transaction to be settled
+                return;
+            }
+
+            let proposal = self
+                .proposals
+                .get_mut(&proposal_id)
+                .expect("Proposal does not exist");
+            if matches!(proposal.action_statuses[index], ActionStatus::Failed { .. }) {
+                if halt_on_failure {
+                    proposal.halted = true;
+                    msg::reply(
+                        DaoEvent::ExecutionHalted {
+                            proposal_id,
+                            action_index: proposal.next_action,
+                        },
+                        0,
+                    )
+                    .unwrap();
+                    return;
+                }
+                // the tokens of the failed transfer aren't reserved anymore
+                if let ProposalAction::Transfer { amount, .. } = action {
+                    self.locked_funds = self.locked_funds.saturating_sub(amount);
+                }
+            }
+            proposal.next_action += 1;
+        }
+    }
+
+    // executes the action of the batch proposal and records its status,
+    // returns `false` if the token transfer of the action has been settled elsewhere
+    // or the execution has been halted until another token transaction is settled
+    async fn execute_action(
+        &mut self,
+        proposal_id: u128,
+        index: usize,
+        action: ProposalAction,
+    ) -> bool {
+        let status = match action {
+            ProposalAction::Transfer { recipient, amount } => {
+                let balance = self.update_balance().await;
+                if !self.transactions.is_empty() {
+                    return self.halt_on_pending_transaction(proposal_id);
+                }
+                let tx_id = self.start_transaction(
+                    TransactionKind::ProposalAction {
+                        proposal_id,
+                        index: index as u32,
+                    },
+                    exec::program_id(),
+                    recipient,
+                    amount,
+                    balance,
+                );
+                return self.execute_transaction(tx_id).await;
+            }
+            ProposalAction::UpdateParameter(parameter) => {
+                self.update_parameter(parameter);
+                ActionStatus::Succeeded {
+                    reply: Vec::new(),
+                    reply_code: None,
+                }
+            }
+            ProposalAction::Execute {
+                destination,
+                payload,
+                value,
+                gas_limit,
+            } => match execute_message(destination, payload, value, gas_limit).await {
+                ExecutionResult::Success { reply, reply_code } => ActionStatus::Succeeded {
+                    reply,
+                    reply_code: Some(reply_code),
+                },
+                ExecutionResult::Failure { error } => ActionStatus::Failed { error },
+            },
+            ProposalAction::ReleaseMilestone {
+                proposal_id: grant_id,
+                index: milestone,
+            } => {
+                let balance = self.update_balance().await;
+                match self.milestone_payout(grant_id, milestone) {
+                    Ok(_) if !self.transactions.is_empty() => {
+                        return self.halt_on_pending_transaction(proposal_id);
+                    }
+                    Ok((applicant, amount)) => {
+                        let tx_id = self.start_transaction(
+                            TransactionKind::ProposalAction {
+                                proposal_id,
+                                index: index as u32,
+                            },
+                            exec::program_id(),
+                            applicant,
+                            amount,
+                            balance,
+                        );
+                        return self.execute_transaction(tx_id).await;
+                    }
+                    Err(error) => ActionStatus::Failed {
+                        error: String::from(error),
+                    },
+                }
+            }
+            ProposalAction::CancelGrant { .. }
+            | ProposalAction::CancelStream { .. }
+            | ProposalAction::CancelRecurringPayment { .. }
+                if !self.transactions.is_empty() =>
+            {
+                return self.halt_on_pending_transaction(proposal_id);
+            }
+            ProposalAction::CancelGrant {
+                proposal_id: grant_id,
+            } => match self.cancel_grant(grant_id) {
+                Ok(()) => ActionStatus::Succeeded {
+                    reply: Vec::new(),
+                    reply_code: None,
+                },
+                Err(error) => ActionStatus::Failed {
+                    error: String::from(error),
+                },
+            },
+            ProposalAction::CancelStream { stream_id } => match self.cancel_stream(stream_id) {
+                Ok(()) => ActionStatus::Succeeded {
+                    reply: Vec::new(),
+                    reply_code: None,
+                },
+                Err(error) => ActionStatus::Failed {
+                    error: String::from(error),
+                },
+            },
+            ProposalAction::CancelRecurringPayment { payment_id } => {
+                match self.cancel_recurring_payment(payment_id) {
+                    Ok(()) => ActionStatus::Succeeded {
+                        reply: Vec::new(),
+                        reply_code: None,
+                    },
+                    Err(error) => ActionStatus::Failed {
+                        error: String::from(error),
+                    },
+                }
+            }
+        };
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.action_statuses[index] = status;
+        }
+        true
+    }
+
+    // halts the execution of the batch proposal on the token transfer that can't be sent
+    // while another token transaction is in progress, the action stays pending
+    // and is executed by `ResumeExecution`
+    fn halt_on_pending_transaction(&mut self, proposal_id: u128) -> bool {
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .expect("Proposal does not exist");
+        proposal.halted = true;
+        msg::reply(
+            DaoEvent::ExecutionHalted {
+                proposal_id,
+                action_index: proposal.next_action,
+            },
+            0,
+        )
+        .unwrap();
+        false
+    }
+
+    /// Resumes the execution of the batch proposal halted on the failed action
+    /// or on the action waiting for another token transaction
+    /// The action is executed again
+    /// Requirements:
+    /// * `msg::source()` must be DAO member
+    /// * The proposal must exist and its execution must be halted
+    /// Arguments:
+    /// * `proposal_id`: the proposal ID
+    async fn resume_execution(&mut self, proposal_id: u128) {
+        self.check_for_membership();
+        let proposal = match self.proposals.get_mut(&proposal_id) {
+            Some(proposal) if proposal.halted => proposal,
+            Some(_) => {
+                panic!("Proposal execution is not halted");
+            }
+            None => {
+                panic!("proposal does not exist");
+            }
+        };
+        proposal.halted = false;
+        proposal.action_statuses[proposal.next_action as usize] = ActionStatus::Pending;
+        self.execute_actions(proposal_id).await;
+    }
+
+    /// Cancels the remaining actions of the batch proposal halted on the failed action
+    /// The tokens reserved for the transfers that haven't been executed are released
+    /// Requirements:
+    /// * `msg::source()` must be DAO member
+    /// * The proposal must exist and its execution must be halted
+    /// Arguments:
+    /// * `proposal_id`: the proposal ID
+    fn cancel_execution(&mut self, proposal_id: u128) {
+        self.check_for_membership();
+        let proposal = match self.proposals.get_mut(&proposal_id) {
+            Some(proposal) if proposal.halted => proposal,
+            Some(_) => {
+                panic!("Proposal execution is not halted");
+            }
+            None => {
+                panic!("proposal does not exist");
+            }
+        };
+        let index = proposal.next_action as usize;
+        let released = proposal.actions()[index..]
+            .iter()
+            .fold(0u128, |released, action| match action {
+                ProposalAction::Transfer { amount, .. } => released.saturating_add(*amount),
+                _ => released,
+            });
+        // the failed action keeps its error, the following actions are never executed
+        for status in &mut proposal.action_statuses[index + 1..] {
+            *status = ActionStatus::Cancelled;
+        }
+        proposal.halted = false;
+        proposal.next_action = proposal.actions().len() as u32;
+        self.locked_funds = self.locked_funds.saturating_sub(released);
+
+        msg::reply(
+            DaoEvent::CancelExecution {
+                proposal_id,
+                released,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    // applies the parameter change approved by the proposal
+    fn update_parameter(&mut self, parameter: DaoParameter) {
+        match parameter {
+            DaoParameter::PeriodDuration(period_duration) => {
+                self.period_duration = period_duration;
+            }
+            DaoParameter::VotingPeriodLength(voting_period_length) => {
+                self.voting_period_length = voting_period_length;
+            }
+            DaoParameter::GracePeriodLength(grace_period_length) => {
+                self.grace_period_length = grace_period_length;
+            }
+            DaoParameter::RevealPeriodLength(reveal_period_length) => {
+                self.reveal_period_length = reveal_period_length;
+            }
+            DaoParameter::DilutionBound(dilution_bound) => {
+                self.dilution_bound = dilution_bound;
+            }
+            DaoParameter::Timelock(timelock) => {
+                self.timelock = timelock;
+            }
+            DaoParameter::VotingMode(voting_mode) => {
+                self.voting_mode = voting_mode;
+            }
+            DaoParameter::Conviction(conviction_config) => {
+                self.conviction_config = conviction_config;
+            }
+            DaoParameter::OptimisticCap(optimistic_cap) => {
+                self.optimistic_cap = optimistic_cap;
+            }
+            DaoParameter::ObjectionThreshold(objection_threshold) => {
+                self.objection_threshold = objection_threshold;
+            }
+        }
+    }
+
+    /// The funding request of the conviction voting pool
+    /// The proposal isn't voted on: members stake their shares on it with `StakeConviction`
+    /// and it can be executed once the accumulated conviction reaches the threshold
+    /// that grows with the share of the available funds the proposal requests.
+    /// Requirements:
+    /// * The proposal can be submitted only by the existing members or their delegate addresses
+    /// * The beneficiary can't be the zero address
+    /// * The amount must be greater than zero
+    /// * The metadata must not exceed `MAX_TITLE_LENGTH`, `MAX_SUMMARY_LENGTH` and `MAX_URI_LENGTH`
+    /// Arguments:
+    /// * `beneficiary`: an actor that will be funded
+    /// * `amount`: the number of fungible tokens that will be sent to the beneficiary
+    /// * `metadata`: the proposal title, summary, URI and content hash
+    fn submit_conviction_proposal(
+        &mut self,
+        beneficiary: &ActorId,
+        amount: u128,
+        metadata: ProposalMetadata,
+    ) {
+        self.check_for_membership();
+        check_metadata(&metadata);
+
+        if beneficiary.is_zero() {
+            panic!("Proposal for the zero address");
+        }
+        if amount == 0 {
+            panic!("Proposal amount must be greater than zero");
+        }
+
+        let proposal_id = self.conviction_proposal_id;
+        self.conviction_proposals.insert(
+            proposal_id,
+            ConvictionProposal {
+                proposer: msg::source(),
+                beneficiary: *beneficiary,
+                amount,
+                metadata,
+                last_update: exec::block_timestamp(),
+                ..ConvictionProposal::default()
+            },
+        );
+        self.conviction_proposal_id = self.conviction_proposal_id.saturating_add(1);
+
+        msg::reply(
+            DaoEvent::SubmitConvictionProposal {
+                proposer: msg::source(),
+                beneficiary: *beneficiary,
+                proposal_id,
+                amount,
+            },
+            0,
+        )
+        .unwrap();
+    }
+
+    /// Stakes the shares of the member on the conviction proposal
+    /// The staked shares can't be withdrawn by `RageQuit` until they are unstaked
+    /// with `WithdrawConviction` or the proposal is executed.
+    /// Requirements:
+    /// * `msg::source()` must be DAO member
+    /// * The proposal must exist and must not be executed
+    /// * The member must have `amount` shares that aren't staked yet
+    /// Arguments:
+    /// * `proposal_id`: the conviction proposal ID
+    /// * `amount`: the number of shares to stake
+    fn stake_conviction(&mut self, proposal_id: u128, amount: u128) {
+        self.check_for_membership();
+        let config = self.conviction_config;
+        let proposal = self
+            .conviction_proposals
+            .get_mut(&proposal_id)
+            .expect("Conviction proposal does not exist");
+        if proposal.executed {
+            panic!("Conviction proposal has been executed");
+        }
+        let member = self.members.get_mut(&msg::source()).unwrap();
+        if amount == 0 || amount > member.shares.saturating_sub(member.staked) {
+            panic!("Not enough unstaked shares");
         }
-        proposal.votes_by_member.push((msg::source(), vote.clone()));
+        proposal.update(&config, exec::block_timestamp());
+        match proposal
+            .stakes
+            .iter_mut()
+            .find(|(id, _)| *id == msg::source())
+        {
+            Some((_, stake)) => *stake = stake.saturating_add(amount),
+            None => proposal.stakes.push((msg::source(), amount)),
+        }
+        proposal.total_staked = proposal.total_staked.saturating_add(amount);
+        member.staked = member.staked.saturating_add(amount);
 
         msg::reply(
-            DaoEvent::SubmitVote {
-                account: msg::source(),
+            DaoEvent::StakeConviction {
+                member: msg::source(),
                 proposal_id,
-                vote,
+                amount,
             },
             0,
         )
         .unwrap();
     }
 
-    /// The proposal processing after the proposal completes during the grace period.
-    /// If the proposal is accepted, the indicated amount of tokens are sent to the applicant.
+    /// Unstakes the shares of the member from the conviction proposal
+    /// The conviction accumulated by the unstaked shares decays over the following periods.
     /// Requirements:
-    /// * The previous proposal must be processed
-    /// * The proposal must exist and be ready for processing
-    /// * The proposal must not be already be processed
+    /// * The proposal must exist
+    /// * `msg::source()` must have staked at least `amount` shares on the proposal
     /// Arguments:
-    /// * `proposal_id`: the proposal ID
-    async fn process_proposal(&mut self, proposal_id: u128) {
-        if proposal_id > 0 && !self.proposals.get(&(&proposal_id - 1)).unwrap().processed {
-            panic!("Previous proposal must be processed");
-        }
-        let proposal = match self.proposals.get_mut(&proposal_id) {
-            Some(proposal) => {
-                if proposal.processed {
-                    panic!("Proposal has already been processed");
-                }
-                if exec::block_timestamp()
-                    < proposal.starting_period
-                        + self.voting_period_length
-                        + self.grace_period_length
-                {
-                    panic!("Proposal is not ready to be processed");
-                }
-                proposal
-            }
-            None => {
-                panic!("proposal does not exist");
-            }
+    /// * `proposal_id`: the conviction proposal ID
+    /// * `amount`: the number of shares to unstake
+    fn withdraw_conviction(&mut self, proposal_id: u128, amount: u128) {
+        let config = self.conviction_config;
+        let proposal = self
+            .conviction_proposals
+            .get_mut(&proposal_id)
+            .expect("Conviction proposal does not exist");
+        let position = proposal
+            .stakes
+            .iter()
+            .position(|(id, stake)| *id == msg::source() && *stake >= amount);
+        let Some(position) = position.filter(|_| amount > 0) else {
+            panic!("Not enough staked shares");
         };
-
-        proposal.processed = true;
-        proposal.did_pass = proposal.yes_votes > proposal.no_votes
-            && proposal.yes_votes * 10_000 / self.total_shares >= proposal.quorum * 100;
-
-        // if funding propoposal has passed
-        if proposal.did_pass {
-            transfer_tokens(
-                &self.approved_token_program_id,
-                &exec::program_id(),
-                &proposal.applicant,
-                proposal.amount,
-            )
-            .await;
+        proposal.update(&config, exec::block_timestamp());
+        proposal.stakes[position].1 -= amount;
+        if proposal.stakes[position].1 == 0 {
+            proposal.stakes.swap_remove(position);
         }
-        self.locked_funds = self.locked_funds.saturating_sub(proposal.amount);
-        let balance = balance(&self.approved_token_program_id, &exec::program_id()).await;
-        if balance == 0 {
-            self.total_shares = 0;
-            self.members = HashMap::new();
+        proposal.total_staked = proposal.total_staked.saturating_sub(amount);
+        if let Some(member) = self.members.get_mut(&msg::source()) {
+            member.staked = member.staked.saturating_sub(amount);
         }
+
         msg::reply(
-            DaoEvent::ProcessProposal {
-                applicant: proposal.applicant,
+            DaoEvent::WithdrawConviction {
+                member: msg::source(),
                 proposal_id,
-                did_pass: proposal.did_pass,
+                amount,
             },
             0,
         )
         .unwrap();
     }
 
+    /// Sends the requested tokens to the beneficiary of the conviction proposal
+    /// and releases the shares staked on it
+    /// Requirements:
+    /// * No other token transaction of DAO must be in progress
+    /// * The proposal must exist and must not be executed
+    /// * Members must have shares staked on the proposal
+    /// * The proposal must request less than `max_ratio` of the funds that aren't locked by the proposals
+    /// * The conviction of the proposal must reach the threshold of the requested amount
+    /// Arguments:
+    /// * `proposal_id`: the conviction proposal ID
+    async fn execute_conviction_proposal(&mut self, proposal_id: u128) {
+        let balance = self.update_balance().await;
+        self.check_no_pending_transactions();
+        let config = self.conviction_config;
+        let funds = balance.saturating_sub(self.locked_funds);
+        let total_shares = self.total_shares;
+        let proposal = self
+            .conviction_proposals
+            .get_mut(&proposal_id)
+            .expect("Conviction proposal does not exist");
+        if proposal.executed {
+            panic!("Conviction proposal has been executed");
+        }
+        // the conviction left after the stakes are withdrawn doesn't support the proposal
+        if proposal.total_staked == 0 {
+            panic!("No shares are staked on the proposal");
+        }
+        let Some(threshold) = config.threshold(proposal.amount, funds, total_shares) else {
+            panic!("Proposal requests too large share of the funds");
+        };
+        proposal.update(&config, exec::block_timestamp());
+        if proposal.conviction < threshold {
+            panic!("Conviction is below the threshold");
+        }
+        let (beneficiary, amount) = (proposal.beneficiary, proposal.amount);
+        let tx_id = self.start_transaction(
+            TransactionKind::ConvictionProposal { proposal_id },
+            exec::program_id(),
+            beneficiary,
+            amount,
+            balance,
+        );
+        self.execute_transaction(tx_id).await;
+    }
+
     /// Withdraws the capital of the member
+    /// The member receives `amount * balance / total_shares` tokens rounded down,
+    /// the remaining dust stays in the DAO treasury.
+    /// If the token transfer fails, the burnt shares are returned to the member.
+    /// While another token transaction is in progress, the shares are burnt
+    /// and queued for the exit paid out by `ClaimExit`.
     /// Requirements:
     /// * `msg::source()` must be DAO member
     /// * The member must have sufficient amount of shares
     /// * The latest proposal the member voted YES must be processed
+    /// * The member must receive at least `min_funds` tokens
     /// Arguments:
     /// * `amount`: The amount of shares the member would like to withdraw
-    async fn ragequit(&mut self, amount: u128) {
+    /// * `min_funds`: The minimum number of tokens the member agrees to receive
+    async fn ragequit(&mut self, amount: u128, min_funds: u128) {
+        let balance = self.update_balance().await;
+        let outstanding_shares = self.outstanding_shares();
         if !self.members.contains_key(&msg::source()) {
             panic!("account is not a DAO member");
         }
@@ -272,6 +2413,9 @@ impl Dao {
         if amount > member.shares {
             panic!("unsufficient shares");
         }
+        if amount > member.shares.saturating_sub(member.staked) {
+            panic!("Shares are staked on conviction proposals");
+        }
         if let Some(proposal_id) = member.highest_index_yes_vote {
             if let Some(proposal) = self.proposals.get(&proposal_id) {
                 if !proposal.processed {
@@ -279,39 +2423,473 @@ impl Dao {
                 }
             }
         }
+        let funds = redeemable_funds(amount, outstanding_shares, balance);
+        if funds < min_funds {
+            panic!("Ragequit returns {funds} tokens that is less than {min_funds}");
+        }
         member.shares = member.shares.saturating_sub(amount);
-        let funds = self.redeemable_funds(amount).await;
-        transfer_tokens(
-            &self.approved_token_program_id,
-            &exec::program_id(),
-            &msg::source(),
+        self.total_shares = self.total_shares.saturating_sub(amount);
+        if !self.transactions.is_empty() {
+            // the queued shares keep their claim on the balance until the exit is paid out
+            let queued = self.queued_exits.entry(msg::source()).or_default();
+            *queued = queued.saturating_add(amount);
+            msg::reply(
+                DaoEvent::RageQuitQueued {
+                    member: msg::source(),
+                    amount,
+                },
+                0,
+            )
+            .unwrap();
+            return;
+        }
+        let tx_id = self.start_transaction(
+            TransactionKind::RageQuit {
+                member: msg::source(),
+                shares: amount,
+            },
+            exec::program_id(),
+            msg::source(),
+            funds,
+            balance,
+        );
+        self.execute_transaction(tx_id).await;
+    }
+
+    /// Pays out the exit queued by `RageQuit` while another token transaction was in progress
+    /// The member receives `shares * balance / (total_shares + queued shares)` tokens rounded down.
+    /// If the token transfer fails, the queued shares are returned to the member.
+    /// Requirements:
+    /// * No other token transaction of DAO must be in progress
+    /// * `msg::source()` must have a queued exit
+    /// * The member must receive at least `min_funds` tokens
+    /// Arguments:
+    /// * `min_funds`: The minimum number of tokens the member agrees to receive
+    async fn claim_exit(&mut self, min_funds: u128) {
+        let balance = self.update_balance().await;
+        self.check_no_pending_transactions();
+        let outstanding_shares = self.outstanding_shares();
+        let shares = self
+            .queued_exits
+            .remove(&msg::source())
+            .expect("The account has no queued exit");
+        let funds = redeemable_funds(shares, outstanding_shares, balance);
+        if funds < min_funds {
+            panic!("Ragequit returns {funds} tokens that is less than {min_funds}");
+        }
+        let tx_id = self.start_transaction(
+            TransactionKind::RageQuit {
+                member: msg::source(),
+                shares,
+            },
+            exec::program_id(),
+            msg::source(),
             funds,
+            balance,
+        );
+        self.execute_transaction(tx_id).await;
+    }
+
+    /// Finishes the token transaction whose reply was not received
+    /// Since DAO executes token transactions one at a time, the DAO balance shows whether
+    /// the transfer has taken place: if so, the transaction is completed,
+    /// otherwise the transfer is sent again
+    /// Requirements:
+    /// * The transaction must exist
+    /// * The DAO balance must either change by exactly the transfer amount or stay the same
+    ///   since the transfer was sent
+    /// Arguments:
+    /// * `tx_id`: the transaction ID
+    async fn continue_transaction(&mut self, tx_id: u64) {
+        if !self.transactions.contains_key(&tx_id) {
+            panic!("Transaction does not exist");
+        }
+        let balance = self.update_balance().await;
+        let batch_proposal = match self.transactions.get(&tx_id) {
+            Some(Transaction {
+                kind: TransactionKind::ProposalAction { proposal_id, .. },
+                ..
+            }) => Some(*proposal_id),
+            _ => None,
+        };
+        let transferred = match self.transactions.get(&tx_id) {
+            Some(transaction) => {
+                // the transfers aren't tagged with the transaction ID, so the balance changed
+                // by other transfers can't tell whether the transaction has taken place
+                let change = if transaction.to == exec::program_id() {
+                    balance.checked_sub(transaction.balance_before)
+                } else {
+                    transaction.balance_before.checked_sub(balance)
+                };
+                match change {
+                    Some(change) if change == transaction.amount => true,
+                    Some(0) => false,
+                    _ => panic!("DAO balance changed by other transfers than transaction {tx_id}"),
+                }
+            }
+            None => {
+                panic!("Transaction does not exist");
+            }
+        };
+        let settled = if transferred {
+            self.complete_transaction(tx_id);
+            true
+        } else {
+            self.execute_transaction(tx_id).await
+        };
+        // the execution of the batch proposal waits for the settled transfer
+        if let (true, Some(proposal_id)) = (settled, batch_proposal) {
+            self.execute_actions(proposal_id).await;
+        }
+    }
+
+    /// Settles the token transaction that `ContinueTransaction` can't reconcile,
+    /// e.g. when the DAO balance was changed by other transfers after the reply was lost
+    /// Requirements:
+    /// * `msg::source()` must be a guardian
+    /// * The transaction must exist
+    /// Arguments:
+    /// * `tx_id`: the transaction ID
+    /// * `transferred`: whether the transfer has taken place according to the token contract,
+    ///   the transaction is completed if so and rolled back otherwise
+    async fn settle_transaction(&mut self, tx_id: u64, transferred: bool) {
+        let batch_proposal = match self.transactions.get(&tx_id) {
+            Some(Transaction {
+                kind: TransactionKind::ProposalAction { proposal_id, .. },
+                ..
+            }) => Some(*proposal_id),
+            Some(_) => None,
+            None => {
+                panic!("Transaction does not exist");
+            }
+        };
+        if !self.approve_guardian_action(GuardianAction::SettleTransaction { tx_id, transferred }) {
+            return;
+        }
+        if transferred {
+            self.complete_transaction(tx_id);
+        } else {
+            self.cancel_transaction(tx_id);
+        }
+        self.update_balance().await;
+        if let Some(proposal_id) = batch_proposal {
+            self.execute_actions(proposal_id).await;
+        }
+    }
+
+    // registers the token transfer as a pending transaction,
+    // so that the operation can't be repeated until the transaction is settled
+    fn start_transaction(
+        &mut self,
+        kind: TransactionKind,
+        from: ActorId,
+        to: ActorId,
+        amount: u128,
+        balance_before: u128,
+    ) -> u64 {
+        let tx_id = self.transaction_id;
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        self.transactions.insert(
+            tx_id,
+            Transaction {
+                kind,
+                from,
+                to,
+                amount,
+                balance_before,
+                attempts: 0,
+            },
+        );
+        tx_id
+    }
+
+    // sends the token transfer of the pending transaction and settles the transaction by the reply,
+    // returns `false` if the transaction has been settled or resent elsewhere
+    async fn execute_transaction(&mut self, tx_id: u64) -> bool {
+        let transaction = self
+            .transactions
+            .get_mut(&tx_id)
+            .expect("Transaction does not exist");
+        transaction.attempts += 1;
+        let (attempt, from, to, amount) = (
+            transaction.attempts,
+            transaction.from,
+            transaction.to,
+            transaction.amount,
+        );
+        let result =
+            transfer_tokens(&self.approved_token_program_id, tx_id, &from, &to, amount).await;
+
+        // the transaction could be settled or resent by `ContinueTransaction` while waiting for the reply
+        if !self.is_current_attempt(tx_id, attempt) {
+            return false;
+        }
+        if result.is_ok() {
+            if let Some(Transaction {
+                kind: TransactionKind::Deposit { .. },
+                balance_before,
+                ..
+            }) = self.transactions.get(&tx_id)
+            {
+                // the shares are minted for the tokens the DAO has actually received
+                let expected_balance = balance_before.saturating_add(amount);
+                let balance = self.update_balance().await;
+                if !self.is_current_attempt(tx_id, attempt) {
+                    return false;
+                }
+                if balance != expected_balance {
+                    panic!(
+                        "Deposit changed DAO balance to {balance} instead of {expected_balance}"
+                    );
+                }
+            }
+            self.complete_transaction(tx_id);
+        } else {
+            self.cancel_transaction(tx_id);
+        }
+        true
+    }
+
+    // checks that the transaction hasn't been settled or resent since the attempt was sent
+    fn is_current_attempt(&self, tx_id: u64, attempt: u32) -> bool {
+        matches!(
+            self.transactions.get(&tx_id),
+            Some(transaction) if transaction.attempts == attempt
         )
-        .await;
-        self.total_shares = self.total_shares.saturating_sub(amount);
+    }
+
+    // applies the state changes of the confirmed token transfer
+    fn complete_transaction(&mut self, tx_id: u64) {
+        let transaction = self
+            .transactions
+            .remove(&tx_id)
+            .expect("Transaction does not exist");
+        match transaction.kind {
+            TransactionKind::Deposit { member, share } => {
+                self.balance = transaction
+                    .balance_before
+                    .saturating_add(transaction.amount);
+                self.members
+                    .entry(member)
+                    .and_modify(|member| member.shares += share)
+                    .or_insert(Member {
+                        shares: share,
+                        highest_index_yes_vote: None,
+                        staked: 0,
+                    });
+                self.total_shares = self.total_shares.saturating_add(share);
+                msg::reply(DaoEvent::Deposit { member, share }, 0).unwrap();
+            }
+            TransactionKind::ExecuteProposal { proposal_id } => {
+                self.balance = transaction
+                    .balance_before
+                    .saturating_sub(transaction.amount);
+                self.locked_funds = self.locked_funds.saturating_sub(transaction.amount);
+                if self.balance == 0 {
+                    self.total_shares = 0;
+                    self.members = HashMap::new();
+                    self.queued_exits = HashMap::new();
+                }
+                msg::reply(DaoEvent::ExecuteProposal { proposal_id }, 0).unwrap();
+            }
+            TransactionKind::RageQuit { member, .. } => {
+                self.balance = transaction
+                    .balance_before
+                    .saturating_sub(transaction.amount);
+                msg::reply(
+                    DaoEvent::RageQuit {
+                        member,
+                        amount: transaction.amount,
+                    },
+                    0,
+                )
+                .unwrap();
+            }
+            TransactionKind::ProposalAction { proposal_id, index } => {
+                self.balance = transaction
+                    .balance_before
+                    .saturating_sub(transaction.amount);
+                self.locked_funds = self.locked_funds.saturating_sub(transaction.amount);
+                if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+                    proposal.action_statuses[index as usize] = ActionStatus::Succeeded {
+                        reply: Vec::new(),
+                        reply_code: None,
+                    };
+                }
+                self.mark_milestone_released(proposal_id, index);
+            }
+            TransactionKind::Milestone { proposal_id, index } => {
+                self.balance = transaction
+                    .balance_before
+                    .saturating_sub(transaction.amount);
+                self.locked_funds = self.locked_funds.saturating_sub(transaction.amount);
+                if let Some(status) = self
+                    .proposals
+                    .get_mut(&proposal_id)
+                    .and_then(|proposal| proposal.milestone_statuses.get_mut(index as usize))
+                {
+                    *status = MilestoneStatus::Released;
+                }
+                msg::reply(
+                    DaoEvent::MilestoneReleased {
+                        proposal_id,
+                        index,
+                        amount: transaction.amount,
+                    },
+                    0,
+                )
+                .unwrap();
+            }
+            TransactionKind::Stream { stream_id } => {
+                self.balance = transaction
+                    .balance_before
+                    .saturating_sub(transaction.amount);
+                self.locked_funds = self.locked_funds.saturating_sub(transaction.amount);
+                if let Some(stream) = self.streams.get_mut(&stream_id) {
+                    stream.claimed = stream.claimed.saturating_add(transaction.amount);
+                }
+                msg::reply(
+                    DaoEvent::StreamClaimed {
+                        stream_id,
+                        amount: transaction.amount,
+                    },
+                    0,
+                )
+                .unwrap();
+            }
+            TransactionKind::RecurringPayment {
+                payment_id,
+                periods,
+            } => {
+                self.balance = transaction
+                    .balance_before
+                    .saturating_sub(transaction.amount);
+                self.locked_funds = self.locked_funds.saturating_sub(transaction.amount);
+                if let Some(payment) = self.recurring_payments.get_mut(&payment_id) {
+                    payment.paid_periods = payment.paid_periods.saturating_add(periods);
+                }
+                msg::reply(
+                    DaoEvent::RecurringPaymentClaimed {
+                        payment_id,
+                        periods,
+                        amount: transaction.amount,
+                    },
+                    0,
+                )
+                .unwrap();
+            }
+            TransactionKind::ConvictionProposal { proposal_id } => {
+                self.balance = transaction
+                    .balance_before
+                    .saturating_sub(transaction.amount);
+                if let Some(proposal) = self.conviction_proposals.get_mut(&proposal_id) {
+                    proposal.executed = true;
+                    proposal.total_staked = 0;
+                    for (account, stake) in proposal.stakes.drain(..) {
+                        if let Some(member) = self.members.get_mut(&account) {
+                            member.staked = member.staked.saturating_sub(stake);
+                        }
+                    }
+                }
+                msg::reply(
+                    DaoEvent::ExecuteConvictionProposal {
+                        proposal_id,
+                        amount: transaction.amount,
+                    },
+                    0,
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    // rolls back the state changes made for the failed token transfer
+    fn cancel_transaction(&mut self, tx_id: u64) {
+        let transaction = self
+            .transactions
+            .remove(&tx_id)
+            .expect("Transaction does not exist");
+        match transaction.kind {
+            // the milestone stays pending, the stream and the recurring payment stay unclaimed
+            TransactionKind::Deposit { .. }
+            | TransactionKind::Milestone { .. }
+            | TransactionKind::Stream { .. }
+            | TransactionKind::RecurringPayment { .. }
+            | TransactionKind::ConvictionProposal { .. } => {}
+            TransactionKind::ExecuteProposal { proposal_id } => {
+                // the proposal can be executed again
+                if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+                    proposal.status = ProposalStatus::Queued;
+                }
+            }
+            TransactionKind::RageQuit { member, shares } => {
+                if let Some(member) = self.members.get_mut(&member) {
+                    member.shares = member.shares.saturating_add(shares);
+                }
+                self.total_shares = self.total_shares.saturating_add(shares);
+            }
+            TransactionKind::ProposalAction { proposal_id, index } => {
+                // the execution of the batch proposal decides how to proceed
+                if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+                    proposal.action_statuses[index as usize] = ActionStatus::Failed {
+                        error: String::from("Transfer failed"),
+                    };
+                }
+                return;
+            }
+        }
         msg::reply(
-            DaoEvent::RageQuit {
-                member: msg::source(),
-                amount: funds,
+            DaoEvent::TransferFailed {
+                recipient: transaction.to,
+                amount: transaction.amount,
             },
             0,
         )
         .unwrap();
     }
 
-    // calculates the funds that the member can redeem based on his shares
-    async fn redeemable_funds(&self, share: u128) -> u128 {
+    // checks that there is no unsettled token transaction, see `continue_transaction`
+    fn check_no_pending_transactions(&self) {
+        if !self.transactions.is_empty() {
+            panic!("Another token transaction is in progress");
+        }
+    }
+
+    // requests the DAO balance from the token contract and remembers it for the state queries
+    async fn update_balance(&mut self) -> u128 {
         let balance = balance(&self.approved_token_program_id, &exec::program_id()).await;
-        (share * balance) / self.total_shares
+        self.balance = balance;
+        balance
     }
 
     // calculates a share a user can receive for his deposited tokens
-    async fn calculate_share(&self, tokens: u128) -> u128 {
-        let balance = balance(&self.approved_token_program_id, &exec::program_id()).await;
-        if balance == 0 {
+    // `balance`: the DAO balance before the deposit
+    fn calculate_share(&self, tokens: u128, balance: u128) -> u128 {
+        let outstanding_shares = self.outstanding_shares();
+        if balance == 0 || outstanding_shares == 0 {
             return tokens;
         }
-        (self.total_shares * tokens) / balance
+        (outstanding_shares * tokens) / balance
+    }
+
+    // returns the shares that have a claim on the DAO balance,
+    // including the burnt shares of the queued exits
+    fn outstanding_shares(&self) -> u128 {
+        self.queued_exits
+            .values()
+            .fold(self.total_shares, |shares, queued| {
+                shares.saturating_add(*queued)
+            })
+    }
+
+    // returns the total vote weight of all members in the voting mode of the proposal
+    fn voting_power(&self, voting_mode: VotingMode) -> u128 {
+        match voting_mode {
+            VotingMode::Linear => self.total_shares,
+            VotingMode::Quadratic => self.members.values().fold(0u128, |power, member| {
+                power.saturating_add(vote_weight(member.shares, VotingMode::Quadratic))
+            }),
+        }
     }
 
     // checks that account is DAO member
@@ -327,41 +2905,402 @@ impl Dao {
     }
 }
 
+// checks that the proposal metadata doesn't exceed the length limits
+fn check_metadata(metadata: &ProposalMetadata) {
+    if metadata.title.len() > MAX_TITLE_LENGTH {
+        panic!("Proposal title exceeds {MAX_TITLE_LENGTH} bytes");
+    }
+    if metadata.summary.len() > MAX_SUMMARY_LENGTH {
+        panic!("Proposal summary exceeds {MAX_SUMMARY_LENGTH} bytes");
+    }
+    if metadata.uri.len() > MAX_URI_LENGTH {
+        panic!("Proposal URI exceeds {MAX_URI_LENGTH} bytes");
+    }
+}
+
+// checks that the signature of the vote is made by the voter with the sr25519 or ed25519 key
+fn verify_signature(voter: &ActorId, payload: &[u8], signature: &VoteSignature) -> bool {
+    let public_key: [u8; 32] = (*voter).into();
+    match signature {
+        VoteSignature::Sr25519(signature) => {
+            let (Ok(public_key), Ok(signature)) = (
+                schnorrkel::PublicKey::from_bytes(&public_key),
+                schnorrkel::Signature::from_bytes(signature),
+            ) else {
+                return false;
+            };
+            public_key
+                .verify_simple(SR25519_SIGNING_CONTEXT, payload, &signature)
+                .is_ok()
+        }
+        VoteSignature::Ed25519(signature) => {
+            let Ok(public_key) = ed25519_dalek::VerifyingKey::from_bytes(&public_key) else {
+                return false;
+            };
+            let signature = ed25519_dalek::Signature::from_bytes(signature);
+            public_key.verify_strict(payload, &signature).is_ok()
+        }
+    }
+}
+
+// sorts the paused or unpaused actions and removes the repeated ones
+fn canonical_actions(mut actions: Vec<PausableAction>) -> Vec<PausableAction> {
+    actions.sort();
+    actions.dedup();
+    actions
+}
+
+// checks whether the options repeat
+fn has_duplicates(mut options: impl Iterator<Item = u32>) -> bool {
+    let mut seen = HashSet::new();
+    options.any(|option| !seen.insert(option))
+}
+
+// the option with the largest weight wins, the option with the lowest index wins the tie
+fn plurality_winner(options: usize, ballots: &[&BallotReceipt]) -> Option<u32> {
+    let mut tally = vec![0u128; options];
+    for BallotReceipt { weight, ballot, .. } in ballots {
+        match ballot {
+            Ballot::Single(option) => {
+                tally[*option as usize] = tally[*option as usize].saturating_add(*weight);
+            }
+            Ballot::Split(weights) => {
+                for (option, weight) in weights {
+                    tally[*option as usize] = tally[*option as usize].saturating_add(*weight);
+                }
+            }
+            Ballot::Ranked(_) => {}
+        }
+    }
+    leader(&tally, (0..options).collect()).map(|option| option as u32)
+}
+
+// the option with the smallest weight is eliminated and its ballots move to the next preference
+// until one of the options gets the majority of the remaining ballots,
+// the option with the highest index is eliminated on the tie
+fn instant_runoff_winner(options: usize, ballots: &[&BallotReceipt]) -> Option<u32> {
+    let mut remaining: Vec<usize> = (0..options).collect();
+    loop {
+        let mut tally = vec![0u128; options];
+        let mut active: u128 = 0;
+        for BallotReceipt { weight, ballot, .. } in ballots {
+            let Ballot::Ranked(ranking) = ballot else {
+                continue;
+            };
+            if let Some(option) = ranking
+                .iter()
+                .map(|option| *option as usize)
+                .find(|option| remaining.contains(option))
+            {
+                tally[option] = tally[option].saturating_add(*weight);
+                active = active.saturating_add(*weight);
+            }
+        }
+        let leader = leader(&tally, remaining.clone())?;
+        if remaining.len() == 1 || tally[leader].saturating_mul(2) > active {
+            return Some(leader as u32);
+        }
+        let loser = remaining
+            .iter()
+            .rev()
+            .copied()
+            .min_by_key(|option| tally[*option])?;
+        remaining.retain(|option| *option != loser);
+    }
+}
+
+// returns the option with the largest non-zero weight, the lowest index wins the tie
+fn leader(tally: &[u128], options: Vec<usize>) -> Option<usize> {
+    options
+        .into_iter()
+        .filter(|option| tally[*option] > 0)
+        .max_by_key(|option| (tally[*option], core::cmp::Reverse(*option)))
+}
+
+// sends the message on behalf of DAO and waits for its reply
+async fn execute_message(
+    destination: ActorId,
+    payload: Vec<u8>,
+    value: u128,
+    gas_limit: u64,
+) -> ExecutionResult {
+    let future = match msg::send_bytes_with_gas_for_reply(destination, payload, gas_limit, value, 0)
+    {
+        Ok(future) => future,
+        Err(error) => {
+            return ExecutionResult::Failure {
+                error: format!("{error:?}"),
+            }
+        }
+    };
+    let message_id = future.waiting_reply_to;
+    execution_replies().insert(message_id, None);
+    let result = future.await;
+    let reply_code = execution_replies().remove(&message_id).flatten();
+    match result {
+        Ok(reply) => ExecutionResult::Success {
+            reply,
+            reply_code: reply_code.expect("Reply code is not recorded"),
+        },
+        Err(error) => ExecutionResult::Failure {
+            error: format!("{error:?}"),
+        },
+    }
+}
+
+// returns the replies awaited by execution proposals, initializing them on the first use
+fn execution_replies() -> &'static mut HashMap<MessageId, Option<ReplyCode>> {
+    unsafe { EXECUTION_REPLIES.get_or_insert_with(HashMap::new) }
+}
+
+// records the code of the reply to the message sent by the execution proposal,
+// the replies to the token transfers aren't recorded
+fn record_reply_code() {
+    let reply_to = msg::reply_to().expect("Unable to get the replied message ID");
+    if let Some(reply_code) = execution_replies().get_mut(&reply_to) {
+        *reply_code = msg::reply_code().ok();
+    }
+}
+
 #[no_mangle]
 extern "C" fn init() {
     let config: InitDao = msg::load().expect("Unable to decode InitDao");
+    if config.dilution_bound == 0 {
+        panic!("Dilution bound must be greater than zero");
+    }
+    let guardians: HashSet<ActorId> = config.guardians.into_iter().collect();
+    if guardians.contains(&ActorId::zero()) {
+        panic!("Guardian can't be the zero address");
+    }
+    if !config.conviction_config.is_valid() {
+        panic!("Invalid conviction config");
+    }
+    if config.objection_threshold > 100 {
+        panic!("Objection threshold can't exceed 100 percent");
+    }
+    if !guardians.is_empty()
+        && (config.guardian_threshold == 0 || config.guardian_threshold as usize > guardians.len())
+    {
+        panic!("Guardian threshold must be between 1 and the number of guardians");
+    }
     let dao = Dao {
         approved_token_program_id: config.approved_token_program_id,
         voting_period_length: config.voting_period_length,
         period_duration: config.period_duration,
+        reveal_period_length: config.reveal_period_length,
+        dilution_bound: config.dilution_bound,
+        voting_mode: config.voting_mode,
+        conviction_config: config.conviction_config,
+        optimistic_cap: config.optimistic_cap,
+        objection_threshold: config.objection_threshold,
+        timelock: config.timelock,
+        guardians,
+        guardian_threshold: config.guardian_threshold,
         ..Dao::default()
     };
     unsafe { DAO = Some(dao) };
 }
 
-#[gstd::async_main]
+#[gstd::async_main(handle_reply = record_reply_code)]
 async fn main() {
     let action: DaoAction = msg::load().expect("Could not load Action");
     let dao: &mut Dao = unsafe { DAO.get_or_insert(Dao::default()) };
+    dao.check_not_paused(&action);
     match action {
-        DaoAction::Deposit { amount } => dao.deposit(amount).await,
+        DaoAction::Deposit { amount, min_shares } => dao.deposit(amount, min_shares).await,
         DaoAction::SubmitFundingProposal {
             applicant,
             amount,
             quorum,
-            details,
+            metadata,
+        } => {
+            dao.submit_funding_proposal(&applicant, amount, quorum, metadata)
+                .await;
+        }
+        DaoAction::SubmitExecuteProposal {
+            destination,
+            payload,
+            value,
+            gas_limit,
+            quorum,
+            metadata,
+        } => {
+            dao.submit_execute_proposal(&destination, payload, value, gas_limit, quorum, metadata);
+        }
+        DaoAction::SubmitBatchProposal {
+            actions,
+            halt_on_failure,
+            quorum,
+            metadata,
+        } => {
+            dao.submit_batch_proposal(actions, halt_on_failure, quorum, metadata)
+                .await;
+        }
+        DaoAction::SubmitMilestoneProposal {
+            applicant,
+            milestones,
+            reviewer,
+            quorum,
+            metadata,
+        } => {
+            dao.submit_milestone_proposal(&applicant, milestones, &reviewer, quorum, metadata)
+                .await;
+        }
+        DaoAction::SubmitStreamProposal {
+            applicant,
+            amount,
+            duration,
+            cliff,
+            quorum,
+            metadata,
+        } => {
+            dao.submit_stream_proposal(&applicant, amount, duration, cliff, quorum, metadata)
+                .await;
+        }
+        DaoAction::SubmitRecurringProposal {
+            applicant,
+            amount,
+            interval,
+            periods,
+            quorum,
+            metadata,
+        } => {
+            dao.submit_recurring_proposal(&applicant, amount, interval, periods, quorum, metadata)
+                .await;
+        }
+        DaoAction::SubmitSignalProposal { metadata, quorum } => {
+            dao.submit_signal_proposal(metadata, quorum);
+        }
+        DaoAction::SubmitOptimisticProposal {
+            applicant,
+            amount,
+            metadata,
         } => {
-            dao.submit_funding_proposal(&applicant, amount, quorum, details)
+            dao.submit_optimistic_proposal(&applicant, amount, metadata)
                 .await;
         }
         DaoAction::ProcessProposal { proposal_id } => {
-            dao.process_proposal(proposal_id).await;
+            dao.process_proposal(proposal_id);
+        }
+        DaoAction::ExecuteProposal { proposal_id } => {
+            dao.execute_proposal(proposal_id).await;
+        }
+        DaoAction::VetoProposal { proposal_id } => {
+            dao.veto_proposal(proposal_id);
+        }
+        DaoAction::Pause { actions } => {
+            dao.pause(actions);
+        }
+        DaoAction::Unpause { actions } => {
+            dao.unpause(actions);
+        }
+        DaoAction::RevokeGuardianApproval { action } => {
+            dao.revoke_guardian_approval(action);
+        }
+        DaoAction::SubmitChoiceProposal {
+            options,
+            method,
+            quorum,
+            metadata,
+        } => {
+            dao.submit_choice_proposal(options, method, quorum, metadata)
+                .await;
         }
         DaoAction::SubmitVote { proposal_id, vote } => {
-            dao.submit_vote(proposal_id, vote);
+            dao.submit_vote(msg::source(), proposal_id, vote, None);
+        }
+        DaoAction::SubmitVoteWithRationale {
+            proposal_id,
+            vote,
+            rationale,
+        } => {
+            dao.submit_vote(msg::source(), proposal_id, vote, Some(rationale));
+        }
+        DaoAction::SubmitVoteBySig {
+            proposal_id,
+            vote,
+            voter,
+            nonce,
+            signature,
+        } => {
+            dao.submit_vote_by_sig(proposal_id, vote, voter, nonce, signature);
+        }
+        DaoAction::SubmitVotes {
+            votes,
+            all_or_nothing,
+        } => {
+            dao.submit_votes(votes, all_or_nothing);
+        }
+        DaoAction::CommitVote {
+            proposal_id,
+            commitment,
+        } => {
+            dao.commit_vote(proposal_id, commitment);
+        }
+        DaoAction::RevealVote {
+            proposal_id,
+            vote,
+            salt,
+        } => {
+            dao.reveal_vote(proposal_id, vote, salt);
+        }
+        DaoAction::SubmitBallot {
+            proposal_id,
+            ballot,
+        } => {
+            dao.submit_ballot(proposal_id, ballot);
+        }
+        DaoAction::RageQuit { amount, min_funds } => {
+            dao.ragequit(amount, min_funds).await;
+        }
+        DaoAction::ClaimExit { min_funds } => {
+            dao.claim_exit(min_funds).await;
+        }
+        DaoAction::ContinueTransaction { tx_id } => {
+            dao.continue_transaction(tx_id).await;
+        }
+        DaoAction::SettleTransaction { tx_id, transferred } => {
+            dao.settle_transaction(tx_id, transferred).await;
+        }
+        DaoAction::ResumeExecution { proposal_id } => {
+            dao.resume_execution(proposal_id).await;
+        }
+        DaoAction::CancelExecution { proposal_id } => {
+            dao.cancel_execution(proposal_id);
+        }
+        DaoAction::ReleaseMilestone { proposal_id, index } => {
+            dao.release_milestone(proposal_id, index).await;
+        }
+        DaoAction::ClaimStream { stream_id } => {
+            dao.claim_stream(stream_id).await;
+        }
+        DaoAction::ClaimRecurringPayment { payment_id } => {
+            dao.claim_recurring_payment(payment_id).await;
+        }
+        DaoAction::SubmitConvictionProposal {
+            beneficiary,
+            amount,
+            metadata,
+        } => {
+            dao.submit_conviction_proposal(&beneficiary, amount, metadata);
+        }
+        DaoAction::StakeConviction {
+            proposal_id,
+            amount,
+        } => {
+            dao.stake_conviction(proposal_id, amount);
+        }
+        DaoAction::WithdrawConviction {
+            proposal_id,
+            amount,
+        } => {
+            dao.withdraw_conviction(proposal_id, amount);
+        }
+        DaoAction::ExecuteConvictionProposal { proposal_id } => {
+            dao.execute_conviction_proposal(proposal_id).await;
         }
-        DaoAction::RageQuit { amount } => {
-            dao.ragequit(amount).await;
+        DaoAction::QueryState { query } => {
+            dao.query_state(query);
         }
     }
 }
@@ -1,5 +1,5 @@
 use ft_io::*;
-use gstd::{msg, ActorId};
+use gstd::{errors::Result, msg, ActorId};
 
 #[allow(unused)]
 pub async fn transfer_from_tokens(token_id: &ActorId, from: &ActorId, to: &ActorId, amount: u128) {
@@ -18,7 +18,17 @@ pub async fn transfer_from_tokens(token_id: &ActorId, from: &ActorId, to: &Actor
     .expect("Error in transfer");
 }
 
-pub async fn transfer_tokens(token_id: &ActorId, from: &ActorId, to: &ActorId, amount: u128) {
+/// Transfers tokens for the DAO transaction `tx_id`
+/// and returns an error if the token contract failed to process the transfer
+/// `FTAction::Transfer` doesn't carry a transaction ID, so `tx_id` only labels the errors
+/// and a repeated transfer isn't recognized by the token contract
+pub async fn transfer_tokens(
+    token_id: &ActorId,
+    tx_id: u64,
+    from: &ActorId,
+    to: &ActorId,
+    amount: u128,
+) -> Result<()> {
     let _transfer_response: FTEvent = msg::send_for_reply_as(
         *token_id,
         FTAction::Transfer {
@@ -29,9 +39,9 @@ pub async fn transfer_tokens(token_id: &ActorId, from: &ActorId, to: &ActorId, a
         0,
         0,
     )
-    .unwrap()
-    .await
-    .expect("Error in transfer");
+    .unwrap_or_else(|error| panic!("Unable to send the transfer of transaction {tx_id}: {error:?}"))
+    .await?;
+    Ok(())
 }
 
 #[allow(unused)]
@@ -16,14 +16,76 @@ pub mod metafns {
         }
     }
 
+    /// Returns all proposals without their summaries, see `proposal_info` for the full metadata
     pub fn all_proposals(state: State) -> Vec<Proposal> {
         state
             .proposals
             .iter()
-            .map(|(_, proposal)| proposal.clone())
+            .map(|(_, proposal)| {
+                let mut proposal = proposal.clone();
+                proposal.metadata.summary = String::new();
+                proposal
+            })
             .collect()
     }
 
+    /// Returns at most `limit` proposals ordered by ID starting from `offset`
+    /// without their summaries and votes, see `proposal_info` for the full proposal
+    pub fn proposals(state: State, offset: u32, limit: u32) -> Vec<(u128, Proposal)> {
+        super::proposals_page(state, &ProposalFilter::default(), offset, limit)
+    }
+
+    /// Returns at most `limit` proposals matching `filter` ordered by ID starting from `offset`
+    /// without their summaries and votes, the offset counts only the matching proposals
+    pub fn proposals_by(
+        state: State,
+        filter: ProposalFilter,
+        offset: u32,
+        limit: u32,
+    ) -> Vec<(u128, Proposal)> {
+        super::proposals_page(state, &filter, offset, limit)
+    }
+
+    /// Returns at most `limit` members ordered by account starting from `offset`
+    pub fn members(mut state: State, offset: u32, limit: u32) -> Vec<(ActorId, Member)> {
+        state.members.sort_by_key(|(account, _)| *account);
+        state
+            .members
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Returns the counts and totals of the DAO without the proposals and members
+    pub fn summary(state: State) -> DaoSummary {
+        let count = |status: ProposalStatus| {
+            state
+                .proposals
+                .iter()
+                .filter(|(_, proposal)| proposal.status == status)
+                .count() as u32
+        };
+        DaoSummary {
+            member_count: state
+                .members
+                .iter()
+                .filter(|(_, member)| member.shares != 0)
+                .count() as u32,
+            total_shares: state.total_shares,
+            balance: state.balance,
+            locked_funds: state.locked_funds,
+            proposal_count: state.proposal_id,
+            submitted_proposals: count(ProposalStatus::Submitted),
+            queued_proposals: count(ProposalStatus::Queued),
+            executed_proposals: count(ProposalStatus::Executed),
+            rejected_proposals: count(ProposalStatus::Rejected),
+            vetoed_proposals: count(ProposalStatus::Vetoed),
+            conviction_proposal_count: state.conviction_proposal_id,
+            pending_transactions: state.transactions.len() as u32,
+        }
+    }
+
     pub fn is_member(state: State, account: ActorId) -> bool {
         state.is_member(&account)
     }
@@ -32,6 +94,7 @@ pub mod metafns {
         state.proposal_id
     }
 
+    /// Panics if the proposal doesn't exist, see `proposal_info_v2`
     pub fn proposal_info(state: State, proposal_id: u128) -> Proposal {
         let (_, proposal) = state
             .proposals
@@ -41,6 +104,70 @@ pub mod metafns {
         proposal.clone()
     }
 
+    /// Returns the proposal or `None` if it doesn't exist
+    pub fn proposal_info_v2(state: State, proposal_id: u128) -> Option<Proposal> {
+        state
+            .proposals
+            .into_iter()
+            .find(|(id, _)| proposal_id == *id)
+            .map(|(_, proposal)| proposal)
+    }
+
+    pub fn execution_result(state: State, proposal_id: u128) -> Option<ExecutionResult> {
+        state
+            .proposals
+            .iter()
+            .find(|(id, _)| proposal_id == *id)
+            .and_then(|(_, proposal)| proposal.execution.clone())
+    }
+
+    /// Returns the receipt of the vote `account` cast on the proposal,
+    /// or `None` if the account hasn't voted on it
+    pub fn vote_receipt(state: State, proposal_id: u128, account: ActorId) -> Option<VoteReceipt> {
+        state
+            .proposals
+            .iter()
+            .find(|(id, _)| proposal_id == *id)
+            .and_then(|(_, proposal)| {
+                proposal
+                    .vote_receipts
+                    .iter()
+                    .find(|(id, _)| account == *id)
+                    .map(|(_, receipt)| receipt.clone())
+            })
+    }
+
+    /// Returns the receipt of the ballot `account` cast on the choice proposal,
+    /// or `None` if the account hasn't submitted a ballot on it
+    pub fn ballot_receipt(
+        state: State,
+        proposal_id: u128,
+        account: ActorId,
+    ) -> Option<BallotReceipt> {
+        state
+            .proposals
+            .iter()
+            .find(|(id, _)| proposal_id == *id)
+            .and_then(|(_, proposal)| {
+                proposal
+                    .ballot_receipts
+                    .iter()
+                    .find(|(id, _)| account == *id)
+                    .map(|(_, receipt)| receipt.clone())
+            })
+    }
+
+    /// Returns the nonce `account` must sign with its next `SubmitVoteBySig` vote
+    pub fn nonce(state: State, account: ActorId) -> u64 {
+        state
+            .nonces
+            .iter()
+            .find(|(id, _)| account == *id)
+            .map(|(_, nonce)| *nonce)
+            .unwrap_or_default()
+    }
+
+    /// Panics if the account isn't a member, see `member_info_v2`
     pub fn member_info(state: State, account: ActorId) -> Member {
         let (_, member) = state
             .members
@@ -51,6 +178,16 @@ pub mod metafns {
         member.clone()
     }
 
+    /// Returns the member or `None` if the account isn't a member or has no shares left
+    pub fn member_info_v2(state: State, account: ActorId) -> Option<Member> {
+        state
+            .members
+            .into_iter()
+            .find(|(id, member)| account == *id && member.shares != 0)
+            .map(|(_, member)| member)
+    }
+
+    /// Panics if the account isn't a member, see `member_power_v2`
     pub fn member_power(state: State, account: ActorId) -> u128 {
         let (_, member) = state
             .members
@@ -60,4 +197,62 @@ pub mod metafns {
 
         member.shares
     }
+
+    /// Returns the shares of the member or 0 if the account isn't a member
+    pub fn member_power_v2(state: State, account: ActorId) -> u128 {
+        state
+            .members
+            .iter()
+            .find(|(id, _)| account == *id)
+            .map(|(_, member)| member.shares)
+            .unwrap_or(0)
+    }
+
+    /// Returns the approximate number of tokens the member would receive for ragequitting
+    /// with `shares`, or 0 if `RageQuit` would fail: the member has insufficient shares,
+    /// the shares are staked on conviction proposals or locked by the YES vote
+    /// on the proposal that isn't processed yet.
+    /// The estimate uses the last treasury balance observed by the DAO, while `RageQuit`
+    /// refreshes the balance first, so pass the estimate as `min_funds` to bound the payout.
+    pub fn preview_ragequit(state: State, account: ActorId, shares: u128) -> u128 {
+        let locked_by_vote = |proposal_id: u128| {
+            state
+                .proposals
+                .iter()
+                .any(|(id, proposal)| *id == proposal_id && !proposal.processed)
+        };
+        match state.members.iter().find(|(id, _)| account == *id) {
+            Some((_, member))
+                if member.shares.saturating_sub(member.staked) >= shares
+                    && !member.highest_index_yes_vote.is_some_and(locked_by_vote) =>
+            {
+                let outstanding_shares = state
+                    .queued_exits
+                    .iter()
+                    .fold(state.total_shares, |total, (_, queued)| {
+                        total.saturating_add(*queued)
+                    });
+                redeemable_funds(shares, outstanding_shares, state.balance)
+            }
+            _ => 0,
+        }
+    }
+}
+
+// returns the page of the matching proposals without their summaries and votes
+fn proposals_page(
+    mut state: DaoState,
+    filter: &ProposalFilter,
+    offset: u32,
+    limit: u32,
+) -> Vec<(u128, Proposal)> {
+    state.proposals.sort_by_key(|(id, _)| *id);
+    state
+        .proposals
+        .into_iter()
+        .filter(|(_, proposal)| filter.matches(proposal))
+        .skip(offset as usize)
+        .take(limit as usize)
+        .map(|(id, proposal)| (id, proposal.preview()))
+        .collect()
 }
@@ -1,8 +1,13 @@
 mod utils;
 
 use dao_light_io::*;
-use gstd::Encode;
-use gtest::System;
+use ed25519_dalek::Signer;
+use ft_io::FTAction;
+use gstd::{
+    errors::{ReplyCode, SuccessReplyReason},
+    ActorId, Encode,
+};
+use gtest::{Gas, Program, System};
 use utils::*;
 
 #[test]
@@ -22,6 +27,39 @@ fn deposit_tokens() {
     )));
 }
 
+#[test]
+fn deposit_slippage() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao(&sys);
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    // tokens sent directly to DAO double the share price
+    assert!(!ft
+        .send(
+            MEMBERS[1],
+            FTAction::Transfer {
+                from: MEMBERS[1].into(),
+                to: 2.into(),
+                amount: 1000,
+            },
+        )
+        .main_failed());
+    // must fail since the deposit mints only 500 shares
+    assert!(deposit_with_min_shares(&dao, MEMBERS[1], 1000, 1000).main_failed());
+
+    let res = deposit_with_min_shares(&dao, MEMBERS[1], 1000, 500);
+    assert!(res.contains(&(
+        MEMBERS[1],
+        DaoEvent::Deposit {
+            member: MEMBERS[1].into(),
+            share: 500,
+        }
+        .encode()
+    )));
+}
+
 #[test]
 fn create_proposal() {
     let sys = System::new();
@@ -73,6 +111,18 @@ fn create_proposal_failures() {
     assert!(proposal(&dao, MEMBERS[0], MEMBERS[2], 300).main_failed());
     // must fail since proposal is made for the zero address
     assert!(proposal(&dao, MEMBERS[0], ZERO_ID, 100).main_failed());
+    let mut long_metadata = metadata("Funding proposal");
+    long_metadata.title = "a".repeat(MAX_TITLE_LENGTH + 1);
+    // must fail since the title exceeds the length limit
+    assert!(proposal_with_metadata(&dao, MEMBERS[0], MEMBERS[2], 100, long_metadata).main_failed());
+    let mut long_metadata = metadata("Funding proposal");
+    long_metadata.summary = "a".repeat(MAX_SUMMARY_LENGTH + 1);
+    // must fail since the summary exceeds the length limit
+    assert!(proposal_with_metadata(&dao, MEMBERS[0], MEMBERS[2], 100, long_metadata).main_failed());
+    let mut long_metadata = metadata("Funding proposal");
+    long_metadata.uri = "a".repeat(MAX_URI_LENGTH + 1);
+    // must fail since the URI exceeds the length limit
+    assert!(proposal_with_metadata(&dao, MEMBERS[0], MEMBERS[2], 100, long_metadata).main_failed());
     // must fail since `msg::source()` is not a dao member
     assert!(proposal(&dao, MEMBERS[1], MEMBERS[0], 100).main_failed());
 }
@@ -192,6 +242,12 @@ fn process_proposal() {
         .encode()
     )));
 
+    let res = execute(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecuteProposal { proposal_id: 0 }.encode()
+    )));
+
     //submit funding proposal
     assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 800).main_failed());
 
@@ -214,6 +270,8 @@ fn process_proposal() {
         }
         .encode()
     )));
+    // must fail since the proposal is rejected
+    assert!(execute(&dao, MEMBERS[0], 1).main_failed());
 }
 
 #[test]
@@ -245,7 +303,7 @@ fn process_proposal_failures() {
 }
 
 #[test]
-fn ragequit_dao() {
+fn process_proposal_dilution_bound() {
     let sys = System::new();
     init_fungible_token(&sys);
     init_dao(&sys);
@@ -253,71 +311,2355 @@ fn ragequit_dao() {
     let ft = sys.get_program(1);
     let dao = sys.get_program(2);
 
-    assert!(!approve(&ft, MEMBERS[1], 2, 1000).main_failed());
-    assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+    for member in MEMBERS {
+        assert!(!approve(&ft, *member, 2, 1000).main_failed());
+        assert!(!deposit(&dao, *member, 1000).main_failed());
+    }
 
-    let res = ragequit(&dao, MEMBERS[1], 800);
+    //submit funding proposal
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 800).main_failed());
+    // the only YES vote is cast while the DAO has 4000 shares
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    // the rest of the members leave the DAO before the proposal is processed
+    assert!(!ragequit(&dao, MEMBERS[1], 1000).main_failed());
+    assert!(!ragequit(&dao, MEMBERS[2], 1000).main_failed());
+    assert!(!ragequit(&dao, MEMBERS[3], 1000).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    // must fail since 1000 total shares are below 4000 / 3
+    let res = process(&dao, MEMBERS[0], 0);
     assert!(res.contains(&(
-        MEMBERS[1],
-        DaoEvent::RageQuit {
-            member: MEMBERS[1].into(),
+        MEMBERS[0],
+        DaoEvent::ProcessProposal {
+            applicant: MEMBERS[2].into(),
+            proposal_id: 0,
+            did_pass: false,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn process_proposal_failed_transfer() {
+    let sys = System::new();
+    let fail_transfers = init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    //submit funding proposal
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 800).main_failed());
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+
+    fail_transfers.set(true);
+    let res = execute(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::TransferFailed {
+            recipient: MEMBERS[2].into(),
             amount: 800,
         }
         .encode()
     )));
 
-    assert!(!approve(&ft, MEMBERS[0], 2, 1000).main_failed());
-    assert!(!approve(&ft, MEMBERS[1], 2, 1000).main_failed());
-    assert!(!approve(&ft, MEMBERS[2], 2, 1000).main_failed());
-    assert!(!approve(&ft, MEMBERS[3], 2, 1000).main_failed());
+    // the proposal stays queued and can be executed again
+    fail_transfers.set(false);
+    let res = execute(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecuteProposal { proposal_id: 0 }.encode()
+    )));
+}
+
+#[test]
+fn process_execute_proposal() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao(&sys);
+    init_echo_program(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    // must fail since the proposal can't send messages to the approved token program
+    assert!(execute_proposal(&dao, MEMBERS[0], 1, b"Transfer".to_vec()).main_failed());
+
+    let res = execute_proposal(&dao, MEMBERS[0], ECHO_ID, b"PING".to_vec());
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitExecuteProposal {
+            proposer: MEMBERS[0].into(),
+            destination: ECHO_ID.into(),
+            proposal_id: 0,
+        }
+        .encode()
+    )));
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    let res = process(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ProcessProposal {
+            applicant: ECHO_ID.into(),
+            proposal_id: 0,
+            did_pass: true,
+        }
+        .encode()
+    )));
+
+    let res = execute(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecuteProposal { proposal_id: 0 }.encode()
+    )));
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert!(matches!(
+        &proposal.execution,
+        Some(ExecutionResult::Success { reply, reply_code })
+            if reply == b"PING" && *reply_code == ReplyCode::Success(SuccessReplyReason::Manual)
+    ));
+}
+
+#[test]
+fn batch_execute_action_reply() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+    init_echo_program(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    let actions = vec![
+        ProposalAction::Execute {
+            destination: ECHO_ID.into(),
+            payload: b"PING".to_vec(),
+            value: 0,
+            gas_limit: 10_000_000_000,
+        },
+        ProposalAction::UpdateParameter(DaoParameter::GracePeriodLength(1000)),
+    ];
+    assert!(!batch_proposal(&dao, MEMBERS[0], actions, true).main_failed());
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    assert!(!execute(&dao, MEMBERS[0], 0).main_failed());
+
+    // the message action records the reply code like the execution proposal
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert!(matches!(
+        &proposal.action_statuses[..],
+        [
+            ActionStatus::Succeeded {
+                reply,
+                reply_code: Some(ReplyCode::Success(SuccessReplyReason::Manual)),
+            },
+            ActionStatus::Succeeded {
+                reply_code: None,
+                ..
+            },
+        ] if reply == b"PING"
+    ));
+}
+
+#[test]
+fn process_batch_proposal() {
+    let sys = System::new();
+    let fail_transfers = init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
 
     assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    let actions = vec![
+        ProposalAction::Transfer {
+            recipient: MEMBERS[2].into(),
+            amount: 100,
+        },
+        ProposalAction::UpdateParameter(DaoParameter::GracePeriodLength(1000)),
+        ProposalAction::Transfer {
+            recipient: MEMBERS[3].into(),
+            amount: 200,
+        },
+    ];
+    // must fail since the proposal has no actions
+    assert!(batch_proposal(&dao, MEMBERS[0], vec![], true).main_failed());
+
+    let res = batch_proposal(&dao, MEMBERS[0], actions, true);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitBatchProposal {
+            proposer: MEMBERS[0].into(),
+            proposal_id: 0,
+            amount: 300,
+        }
+        .encode()
+    )));
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    // must fail since the execution is not halted
+    assert!(resume_execution(&dao, MEMBERS[0], 0).main_failed());
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+
+    fail_transfers.set(true);
+    let res = execute(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecutionHalted {
+            proposal_id: 0,
+            action_index: 0,
+        }
+        .encode()
+    )));
+
+    fail_transfers.set(false);
+    let res = resume_execution(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecuteProposal { proposal_id: 0 }.encode()
+    )));
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert!(proposal
+        .action_statuses
+        .iter()
+        .all(|status| matches!(status, ActionStatus::Succeeded { .. })));
+    assert_eq!(state.grace_period_length, 1000);
+    assert_eq!(state.locked_funds, 0);
+}
+
+#[test]
+fn batch_execution_with_pending_transaction() {
+    let sys = System::new();
+    let (_, lose_replies) = init_mock_token_with_lost_replies(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    let actions = vec![
+        ProposalAction::Transfer {
+            recipient: MEMBERS[2].into(),
+            amount: 100,
+        },
+        ProposalAction::UpdateParameter(DaoParameter::GracePeriodLength(1000)),
+    ];
+    assert!(!batch_proposal(&dao, MEMBERS[0], actions, false).main_failed());
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+
+    lose_replies.set(true);
     assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
-    assert!(!deposit(&dao, MEMBERS[2], 1000).main_failed());
-    assert!(!deposit(&dao, MEMBERS[3], 1000).main_failed());
+    lose_replies.set(false);
 
-    //submit funding proposal
-    assert!(!proposal(&dao, MEMBERS[1], MEMBERS[2], 800).main_failed());
+    // the transfer waits for the deposit instead of failing, even if the batch doesn't halt on failures
+    let res = execute(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecutionHalted {
+            proposal_id: 0,
+            action_index: 0,
+        }
+        .encode()
+    )));
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert!(matches!(
+        proposal.action_statuses[..],
+        [ActionStatus::Pending, ActionStatus::Pending]
+    ));
+    assert_eq!(state.locked_funds, 100);
 
-    // votes YES
+    assert!(!continue_transaction(&dao, MEMBERS[1], 1).main_failed());
+    let res = resume_execution(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecuteProposal { proposal_id: 0 }.encode()
+    )));
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert!(proposal
+        .action_statuses
+        .iter()
+        .all(|status| matches!(status, ActionStatus::Succeeded { .. })));
+    assert_eq!(state.locked_funds, 0);
+    assert_eq!(state.balance, 1900);
+}
+
+#[test]
+fn cancel_batch_execution() {
+    let sys = System::new();
+    let fail_transfers = init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    // must fail since the periods can't be zero
+    for parameter in [
+        DaoParameter::PeriodDuration(0),
+        DaoParameter::VotingPeriodLength(0),
+    ] {
+        assert!(batch_proposal(
+            &dao,
+            MEMBERS[0],
+            vec![ProposalAction::UpdateParameter(parameter)],
+            true
+        )
+        .main_failed());
+    }
+
+    let actions = vec![
+        ProposalAction::Transfer {
+            recipient: MEMBERS[2].into(),
+            amount: 100,
+        },
+        ProposalAction::UpdateParameter(DaoParameter::GracePeriodLength(1000)),
+        ProposalAction::Transfer {
+            recipient: MEMBERS[3].into(),
+            amount: 200,
+        },
+    ];
+    assert!(!batch_proposal(&dao, MEMBERS[0], actions, true).main_failed());
     assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
-    assert!(!vote(&dao, MEMBERS[1], 0, Vote::Yes).main_failed());
-    assert!(!vote(&dao, MEMBERS[2], 0, Vote::Yes).main_failed());
-    assert!(!vote(&dao, MEMBERS[3], 0, Vote::Yes).main_failed());
 
     sys.spend_blocks(1000001);
 
     assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    // must fail since the execution is not halted
+    assert!(cancel_execution(&dao, MEMBERS[0], 0).main_failed());
 
-    let res = ragequit(&dao, MEMBERS[1], 800);
+    fail_transfers.set(true);
+    assert!(!execute(&dao, MEMBERS[0], 0).main_failed());
+    fail_transfers.set(false);
+
+    // must fail since the account is not a member
+    assert!(cancel_execution(&dao, MEMBERS[1], 0).main_failed());
+    let res = cancel_execution(&dao, MEMBERS[0], 0);
     assert!(res.contains(&(
-        MEMBERS[1],
-        DaoEvent::RageQuit {
-            member: MEMBERS[1].into(),
-            amount: 647,
+        MEMBERS[0],
+        DaoEvent::CancelExecution {
+            proposal_id: 0,
+            released: 300,
         }
         .encode()
     )));
+    // must fail since the execution has been cancelled
+    assert!(resume_execution(&dao, MEMBERS[0], 0).main_failed());
+    assert!(cancel_execution(&dao, MEMBERS[0], 0).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert!(matches!(
+        proposal.action_statuses[..],
+        [
+            ActionStatus::Failed { .. },
+            ActionStatus::Cancelled,
+            ActionStatus::Cancelled
+        ]
+    ));
+    assert_eq!(state.grace_period_length, 0);
+    assert_eq!(state.locked_funds, 0);
+    assert_eq!(state.balance, 1000);
 }
 
 #[test]
-fn ragequit_failures() {
+fn milestone_proposal_payouts() {
     let sys = System::new();
-    init_fungible_token(&sys);
+    init_mock_token(&sys);
     init_dao(&sys);
 
-    let ft = sys.get_program(1);
     let dao = sys.get_program(2);
 
-    assert!(!approve(&ft, MEMBERS[0], 2, 1000).main_failed());
     assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
-    // must fail since the account is not a DAO member
-    assert!(ragequit(&dao, MEMBERS[1], 800).main_failed());
-    // must fail since the account has no sufficient shares
-    assert!(ragequit(&dao, MEMBERS[0], 1100).main_failed());
-    //submit funding proposal
-    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 800).main_failed());
+    // must fail since the proposal has no milestones
+    assert!(milestone_proposal(&dao, MEMBERS[0], MEMBERS[2], &[], MEMBERS[1]).main_failed());
+    // must fail since DAO doesn't have enough funds
+    assert!(
+        milestone_proposal(&dao, MEMBERS[0], MEMBERS[2], &[800, 800], MEMBERS[1]).main_failed()
+    );
+    // must fail since the milestone details exceed the length limit
+    assert!(dao
+        .send(
+            MEMBERS[0],
+            DaoAction::SubmitMilestoneProposal {
+                applicant: MEMBERS[2].into(),
+                milestones: vec![Milestone {
+                    amount: 100,
+                    details: "a".repeat(MAX_MILESTONE_DETAILS_LENGTH + 1),
+                }],
+                reviewer: MEMBERS[1].into(),
+                quorum: 80,
+                metadata: metadata("Milestone proposal"),
+            },
+        )
+        .main_failed());
+
+    let res = milestone_proposal(&dao, MEMBERS[0], MEMBERS[2], &[300, 200], MEMBERS[1]);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitMilestoneProposal {
+            proposer: MEMBERS[0].into(),
+            applicant: MEMBERS[2].into(),
+            proposal_id: 0,
+            amount: 500,
+        }
+        .encode()
+    )));
     assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
-    // must fail since cant ragequit until highest index proposal member voted YES on is processed
-    assert!(ragequit(&dao, MEMBERS[0], 100).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    // must fail since the grant is not executed
+    assert!(release_milestone(&dao, MEMBERS[1], 0, 0).main_failed());
+    assert!(!execute(&dao, MEMBERS[0], 0).main_failed());
+
+    // must fail since only the reviewer can release milestones
+    assert!(release_milestone(&dao, MEMBERS[0], 0, 0).main_failed());
+    let res = release_milestone(&dao, MEMBERS[1], 0, 0);
+    assert!(res.contains(&(
+        MEMBERS[1],
+        DaoEvent::MilestoneReleased {
+            proposal_id: 0,
+            index: 0,
+            amount: 300,
+        }
+        .encode()
+    )));
+    // must fail since the milestone has already been released
+    assert!(release_milestone(&dao, MEMBERS[1], 0, 0).main_failed());
+
+    // members cancel the rest of the grant
+    let actions = vec![ProposalAction::CancelGrant { proposal_id: 0 }];
+    assert!(!batch_proposal(&dao, MEMBERS[0], actions, true).main_failed());
+    assert!(!vote(&dao, MEMBERS[0], 1, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 1).main_failed());
+    let res = execute(&dao, MEMBERS[0], 1);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecuteProposal { proposal_id: 1 }.encode()
+    )));
+    // must fail since the milestone has been cancelled
+    assert!(release_milestone(&dao, MEMBERS[1], 0, 1).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, grant) = state
+        .proposals
+        .iter()
+        .find(|(proposal_id, _)| *proposal_id == 0)
+        .unwrap();
+    assert!(matches!(
+        grant.milestone_statuses[..],
+        [MilestoneStatus::Released, MilestoneStatus::Cancelled]
+    ));
+    assert_eq!(state.locked_funds, 0);
+    assert_eq!(state.balance, 700);
+}
+
+#[test]
+fn stream_proposal_payouts() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    // must fail since the cliff is longer than the stream
+    assert!(stream_proposal(&dao, MEMBERS[0], MEMBERS[2], 600, 1000, 2000).main_failed());
+
+    let duration = 10_000_000_000;
+    let res = stream_proposal(&dao, MEMBERS[0], MEMBERS[2], 600, duration, duration / 10);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitStreamProposal {
+            proposer: MEMBERS[0].into(),
+            applicant: MEMBERS[2].into(),
+            proposal_id: 0,
+            amount: 600,
+        }
+        .encode()
+    )));
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    assert!(!execute(&dao, MEMBERS[0], 0).main_failed());
+
+    // must fail since only the recipient can claim the stream
+    assert!(claim_stream(&dao, MEMBERS[0], 0).main_failed());
+    // must fail since nothing is vested before the cliff
+    assert!(claim_stream(&dao, MEMBERS[2], 0).main_failed());
+
+    sys.spend_blocks(2000000);
+
+    assert!(!claim_stream(&dao, MEMBERS[2], 0).main_failed());
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, stream) = &state.streams[0];
+    assert!(stream.claimed > 0 && stream.claimed < 600);
+    assert_eq!(state.locked_funds, 600 - stream.claimed);
+
+    // members cancel the stream
+    let actions = vec![ProposalAction::CancelStream { stream_id: 0 }];
+    assert!(!batch_proposal(&dao, MEMBERS[0], actions, true).main_failed());
+    assert!(!vote(&dao, MEMBERS[0], 1, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 1).main_failed());
+    assert!(!execute(&dao, MEMBERS[0], 1).main_failed());
+
+    // the unvested tokens are returned to the treasury
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, stream) = &state.streams[0];
+    let cancelled_at = stream.cancelled_at.expect("Stream is not cancelled");
+    let vested = stream.vested(cancelled_at);
+    assert!(vested < 600);
+    assert_eq!(state.locked_funds, vested - stream.claimed);
+
+    // the vested tokens can still be claimed
+    let res = claim_stream(&dao, MEMBERS[2], 0);
+    assert!(res.contains(&(
+        MEMBERS[2],
+        DaoEvent::StreamClaimed {
+            stream_id: 0,
+            amount: vested - stream.claimed,
+        }
+        .encode()
+    )));
+    assert!(claim_stream(&dao, MEMBERS[2], 0).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.locked_funds, 0);
+    assert_eq!(state.balance, 1000 - vested);
+}
+
+#[test]
+fn recurring_proposal_payouts() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    // must fail since the proposal has no periods
+    assert!(recurring_proposal(&dao, MEMBERS[0], MEMBERS[2], 100, 1000, 0).main_failed());
+    // must fail since DAO doesn't have enough funds for all periods
+    assert!(recurring_proposal(&dao, MEMBERS[0], MEMBERS[2], 100, 1000, 11).main_failed());
+
+    let interval = 500_000_000;
+    let res = recurring_proposal(&dao, MEMBERS[0], MEMBERS[2], 100, interval, 5);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitRecurringProposal {
+            proposer: MEMBERS[0].into(),
+            applicant: MEMBERS[2].into(),
+            proposal_id: 0,
+            amount: 500,
+        }
+        .encode()
+    )));
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    assert!(!execute(&dao, MEMBERS[0], 0).main_failed());
+
+    // must fail since only the recipient can claim the payment
+    assert!(claim_recurring_payment(&dao, MEMBERS[0], 0).main_failed());
+    // must fail since no period has elapsed
+    assert!(claim_recurring_payment(&dao, MEMBERS[2], 0).main_failed());
+
+    sys.spend_blocks(1000000);
+
+    let res = claim_recurring_payment(&dao, MEMBERS[2], 0);
+    assert!(res.contains(&(
+        MEMBERS[2],
+        DaoEvent::RecurringPaymentClaimed {
+            payment_id: 0,
+            periods: 2,
+            amount: 200,
+        }
+        .encode()
+    )));
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.locked_funds, 300);
+
+    // members cancel the payment
+    let actions = vec![ProposalAction::CancelRecurringPayment { payment_id: 0 }];
+    assert!(!batch_proposal(&dao, MEMBERS[0], actions, true).main_failed());
+    assert!(!vote(&dao, MEMBERS[0], 1, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 1).main_failed());
+    assert!(!execute(&dao, MEMBERS[0], 1).main_failed());
+
+    // the tokens of the remaining periods are returned to the treasury
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, payment) = &state.recurring_payments[0];
+    let cancelled_at = payment.cancelled_at.expect("Payment is not cancelled");
+    let unpaid = payment.unpaid_periods(cancelled_at);
+    assert!(payment.due_periods(cancelled_at) < 5);
+    assert_eq!(state.locked_funds, 100 * unpaid as u128);
+
+    // the elapsed periods can still be claimed
+    assert!(!claim_recurring_payment(&dao, MEMBERS[2], 0).main_failed());
+    assert!(claim_recurring_payment(&dao, MEMBERS[2], 0).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.locked_funds, 0);
+    assert_eq!(state.balance, 800 - 100 * unpaid as u128);
+}
+
+#[test]
+fn conviction_proposal_funding() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+
+    let res = conviction_proposal(&dao, MEMBERS[0], MEMBERS[2], 100);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitConvictionProposal {
+            proposer: MEMBERS[0].into(),
+            beneficiary: MEMBERS[2].into(),
+            proposal_id: 0,
+            amount: 100,
+        }
+        .encode()
+    )));
+    // requests a quarter of the funds that exceeds the maximum ratio
+    assert!(!conviction_proposal(&dao, MEMBERS[0], MEMBERS[2], 500).main_failed());
+
+    // must fail since the account is not a member
+    assert!(stake_conviction(&dao, MEMBERS[2], 0, 100).main_failed());
+    // must fail since the member doesn't have enough shares
+    assert!(stake_conviction(&dao, MEMBERS[0], 0, 1001).main_failed());
+
+    let res = stake_conviction(&dao, MEMBERS[0], 0, 1000);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::StakeConviction {
+            member: MEMBERS[0].into(),
+            proposal_id: 0,
+            amount: 1000,
+        }
+        .encode()
+    )));
+    // must fail since all the shares are already staked
+    assert!(stake_conviction(&dao, MEMBERS[0], 1, 1).main_failed());
+    // must fail since the staked shares can't be withdrawn
+    assert!(ragequit(&dao, MEMBERS[0], 1).main_failed());
+    // must fail since the conviction hasn't accumulated yet
+    assert!(execute_conviction(&dao, MEMBERS[0], 0).main_failed());
+
+    assert!(!stake_conviction(&dao, MEMBERS[1], 0, 500).main_failed());
+    assert!(!withdraw_conviction(&dao, MEMBERS[1], 0, 500).main_failed());
+    // must fail since the member has nothing staked
+    assert!(withdraw_conviction(&dao, MEMBERS[1], 0, 1).main_failed());
+
+    assert!(!stake_conviction(&dao, MEMBERS[1], 1, 1000).main_failed());
+
+    sys.spend_blocks(100);
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = state
+        .conviction_proposals
+        .iter()
+        .find(|(id, _)| *id == 0)
+        .expect("Proposal does not exist");
+    let threshold = state
+        .conviction_config
+        .threshold(100, state.balance, state.total_shares)
+        .expect("Proposal exceeds the maximum ratio");
+    assert!(proposal.conviction_at(&state.conviction_config, sys.block_timestamp()) >= threshold);
+
+    // must fail since the proposal requests too large share of the funds
+    assert!(execute_conviction(&dao, MEMBERS[1], 1).main_failed());
+
+    let res = execute_conviction(&dao, MEMBERS[1], 0);
+    assert!(res.contains(&(
+        MEMBERS[1],
+        DaoEvent::ExecuteConvictionProposal {
+            proposal_id: 0,
+            amount: 100,
+        }
+        .encode()
+    )));
+    // must fail since the proposal has been executed
+    assert!(execute_conviction(&dao, MEMBERS[1], 0).main_failed());
+
+    // the stakes are released
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.balance, 1900);
+    let (_, member) = state
+        .members
+        .iter()
+        .find(|(id, _)| *id == MEMBERS[0].into())
+        .expect("Member does not exist");
+    assert_eq!(member.staked, 0);
+    assert!(!ragequit(&dao, MEMBERS[0], 1000).main_failed());
+}
+
+#[test]
+fn conviction_config_and_stakes() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    sys.init_logger();
+    let dao = Program::current(&sys);
+    // must fail since the zero weight makes the threshold zero
+    let res = dao.send(
+        MEMBERS[0],
+        InitDao {
+            conviction_config: ConvictionConfig {
+                weight: 0,
+                ..CONVICTION_CONFIG
+            },
+            ..dao_config()
+        },
+    );
+    assert!(res.main_failed());
+
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+
+    // must fail since the zero weight is invalid
+    let actions = vec![ProposalAction::UpdateParameter(DaoParameter::Conviction(
+        ConvictionConfig {
+            weight: 0,
+            ..CONVICTION_CONFIG
+        },
+    ))];
+    assert!(batch_proposal(&dao, MEMBERS[0], actions, true).main_failed());
+
+    assert!(!conviction_proposal(&dao, MEMBERS[0], MEMBERS[2], 100).main_failed());
+    // must fail since nothing is staked
+    assert!(execute_conviction(&dao, MEMBERS[0], 0).main_failed());
+
+    assert!(!stake_conviction(&dao, MEMBERS[0], 0, 1000).main_failed());
+    sys.spend_blocks(100);
+    assert!(!withdraw_conviction(&dao, MEMBERS[0], 0, 1000).main_failed());
+
+    // the conviction stays above the threshold while it decays
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.conviction_proposals[0];
+    let threshold = state
+        .conviction_config
+        .threshold(100, state.balance, state.total_shares)
+        .expect("Proposal exceeds the maximum ratio");
+    assert!(proposal.conviction_at(&state.conviction_config, sys.block_timestamp()) >= threshold);
+    // must fail since the stakes have been withdrawn
+    assert!(execute_conviction(&dao, MEMBERS[0], 0).main_failed());
+}
+
+#[test]
+fn optimistic_proposal_objections() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+
+    // must fail since the amount exceeds the optimistic cap
+    assert!(optimistic_proposal(&dao, MEMBERS[0], MEMBERS[2], 501).main_failed());
+    // must fail since the account is not a member
+    assert!(optimistic_proposal(&dao, MEMBERS[2], MEMBERS[2], 100).main_failed());
+
+    let res = optimistic_proposal(&dao, MEMBERS[0], MEMBERS[2], 100);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitOptimisticProposal {
+            proposer: MEMBERS[0].into(),
+            applicant: MEMBERS[2].into(),
+            proposal_id: 0,
+            amount: 100,
+        }
+        .encode()
+    )));
+    assert!(!optimistic_proposal(&dao, MEMBERS[0], MEMBERS[2], 200).main_failed());
+    // must fail since the pending optimistic proposals would exceed the cap
+    assert!(optimistic_proposal(&dao, MEMBERS[0], MEMBERS[2], 201).main_failed());
+    // must fail since the objection threshold can't exceed 100 percent
+    assert!(batch_proposal(
+        &dao,
+        MEMBERS[0],
+        vec![ProposalAction::UpdateParameter(
+            DaoParameter::ObjectionThreshold(101)
+        )],
+        true
+    )
+    .main_failed());
+
+    sys.spend_blocks(100);
+    // the objection of a half of the shares exceeds the threshold
+    assert!(!vote(&dao, MEMBERS[1], 1, Vote::No).main_failed());
+
+    sys.spend_blocks(1100001);
+
+    // the proposal without objections passes
+    let res = process(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ProcessProposal {
+            applicant: MEMBERS[2].into(),
+            proposal_id: 0,
+            did_pass: true,
+        }
+        .encode()
+    )));
+    let res = process(&dao, MEMBERS[0], 1);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ProcessProposal {
+            applicant: MEMBERS[2].into(),
+            proposal_id: 1,
+            did_pass: false,
+        }
+        .encode()
+    )));
+
+    let res = execute(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecuteProposal { proposal_id: 0 }.encode()
+    )));
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.balance, 1900);
+    assert_eq!(state.locked_funds, 0);
+    assert_eq!(state.optimistic_exposure, 0);
+
+    let sys = System::new();
+    init_mock_token(&sys);
+    // must fail since the objection threshold can't exceed 100 percent
+    let res = Program::current(&sys).send(
+        MEMBERS[0],
+        InitDao {
+            objection_threshold: 101,
+            ..dao_config()
+        },
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn vote_receipts() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 400).main_failed());
+    assert!(!deposit(&dao, MEMBERS[2], 100).main_failed());
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[3], 100).main_failed());
+
+    // must fail since the reason is too long
+    let reason = VoteRationale::Reason("a".repeat(MAX_REASON_LENGTH + 1));
+    assert!(vote_with_rationale(&dao, MEMBERS[0], 0, Vote::Yes, reason).main_failed());
+
+    let reason = VoteRationale::Reason(String::from("The grant is well scoped"));
+    let res = vote_with_rationale(&dao, MEMBERS[0], 0, Vote::Yes, reason);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitVote {
+            account: MEMBERS[0].into(),
+            proposal_id: 0,
+            vote: Vote::Yes,
+        }
+        .encode()
+    )));
+    let hash = VoteRationale::Hash([1; 32]);
+    assert!(!vote_with_rationale(&dao, MEMBERS[1], 0, Vote::No, hash).main_failed());
+    assert!(!vote(&dao, MEMBERS[2], 0, Vote::No).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    let receipt = |member: u64| {
+        proposal
+            .vote_receipts
+            .iter()
+            .find(|(id, _)| *id == member.into())
+            .map(|(_, receipt)| receipt)
+            .expect("Receipt does not exist")
+    };
+    assert!(matches!(
+        receipt(MEMBERS[0]),
+        VoteReceipt {
+            vote: Vote::Yes,
+            weight: 1000,
+            rationale: Some(VoteRationale::Reason(reason)),
+            ..
+        } if reason == "The grant is well scoped"
+    ));
+    assert!(matches!(
+        receipt(MEMBERS[1]),
+        VoteReceipt {
+            vote: Vote::No,
+            weight: 400,
+            rationale: Some(VoteRationale::Hash(hash)),
+            ..
+        } if *hash == [1; 32]
+    ));
+    assert!(matches!(
+        receipt(MEMBERS[2]),
+        VoteReceipt {
+            vote: Vote::No,
+            weight: 100,
+            rationale: None,
+            ..
+        }
+    ));
+    assert!(receipt(MEMBERS[0]).timestamp < receipt(MEMBERS[2]).timestamp);
+    // the receipts are listed in the order the votes were counted
+    assert!(proposal
+        .vote_receipts
+        .iter()
+        .map(|(_, receipt)| receipt.sequence)
+        .eq(0..3));
+
+    let receipt: Option<VoteReceipt> =
+        read_state_using_wasm(&dao, "vote_receipt", (0u128, ActorId::from(MEMBERS[1])));
+    assert!(matches!(
+        receipt,
+        Some(VoteReceipt {
+            vote: Vote::No,
+            weight: 400,
+            sequence: 1,
+            ..
+        })
+    ));
+    // the account hasn't voted on the proposal
+    let receipt: Option<VoteReceipt> =
+        read_state_using_wasm(&dao, "vote_receipt", (0u128, ActorId::from(MEMBERS[3])));
+    assert!(receipt.is_none());
+}
+
+#[test]
+fn signed_votes() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+    let relayer = 20;
+    let sr25519 = sr25519_signer();
+    let sr25519_voter = sr25519.public.to_bytes();
+    let ed25519 = ed25519_signer();
+    let ed25519_voter = ed25519.verifying_key().to_bytes();
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    for voter in [sr25519_voter, ed25519_voter] {
+        let res = dao.send(
+            voter,
+            DaoAction::Deposit {
+                amount: 1000,
+                min_shares: 0,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[1], 100).main_failed());
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[1], 100).main_failed());
+
+    let payload = vote_signing_payload(2.into(), 0, &Vote::Yes, 0);
+    let signature = sr25519
+        .sign_simple(SR25519_SIGNING_CONTEXT, &payload)
+        .to_bytes();
+    let res = vote_by_sig(
+        &dao,
+        relayer,
+        0,
+        Vote::Yes,
+        sr25519_voter,
+        0,
+        VoteSignature::Sr25519(signature),
+    );
+    assert!(res.contains(&(
+        relayer,
+        DaoEvent::SubmitVote {
+            account: sr25519_voter.into(),
+            proposal_id: 0,
+            vote: Vote::Yes,
+        }
+        .encode()
+    )));
+    // must fail since the nonce has been used, the voter hasn't voted on the second proposal
+    let payload = vote_signing_payload(2.into(), 1, &Vote::Yes, 0);
+    let signature = sr25519
+        .sign_simple(SR25519_SIGNING_CONTEXT, &payload)
+        .to_bytes();
+    let signature = VoteSignature::Sr25519(signature);
+    assert!(vote_by_sig(&dao, relayer, 1, Vote::Yes, sr25519_voter, 0, signature).main_failed());
+
+    // must fail since the signature of the correct payload is made by another key
+    let payload = vote_signing_payload(2.into(), 1, &Vote::Yes, 1);
+    let signature = VoteSignature::Ed25519(ed25519.sign(&payload).to_bytes());
+    assert!(vote_by_sig(&dao, relayer, 1, Vote::Yes, sr25519_voter, 1, signature).main_failed());
+    let signature = sr25519
+        .sign_simple(SR25519_SIGNING_CONTEXT, &payload)
+        .to_bytes();
+    let signature = VoteSignature::Sr25519(signature);
+    assert!(!vote_by_sig(&dao, relayer, 1, Vote::Yes, sr25519_voter, 1, signature).main_failed());
+
+    // must fail since the signature is made for another vote
+    let payload = vote_signing_payload(2.into(), 0, &Vote::Yes, 0);
+    let signature = VoteSignature::Ed25519(ed25519.sign(&payload).to_bytes());
+    assert!(vote_by_sig(&dao, relayer, 0, Vote::No, ed25519_voter, 0, signature).main_failed());
+    // must fail since the nonce is ahead of the voter nonce
+    let payload = vote_signing_payload(2.into(), 0, &Vote::No, 1);
+    let signature = VoteSignature::Ed25519(ed25519.sign(&payload).to_bytes());
+    assert!(vote_by_sig(&dao, relayer, 0, Vote::No, ed25519_voter, 1, signature).main_failed());
+
+    let payload = vote_signing_payload(2.into(), 0, &Vote::No, 0);
+    let signature = VoteSignature::Ed25519(ed25519.sign(&payload).to_bytes());
+    assert!(!vote_by_sig(&dao, relayer, 0, Vote::No, ed25519_voter, 0, signature).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let proposal = |id: u128| {
+        let (_, proposal) = state
+            .proposals
+            .iter()
+            .find(|(proposal_id, _)| *proposal_id == id)
+            .unwrap();
+        (proposal.yes_votes, proposal.no_votes)
+    };
+    assert_eq!(proposal(0), (1000, 1000));
+    assert_eq!(proposal(1), (1000, 0));
+    for (voter, expected) in [(sr25519_voter, 2), (ed25519_voter, 1)] {
+        assert!(state
+            .nonces
+            .iter()
+            .any(|(id, nonce)| *id == voter.into() && *nonce == expected));
+    }
+}
+
+#[test]
+fn commit_reveal_voting() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao_with_reveal_period(&sys, 500000);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 100).main_failed());
+    assert!(!deposit(&dao, MEMBERS[2], 100).main_failed());
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[3], 100).main_failed());
+
+    // must fail since the votes on the proposal are hidden
+    assert!(vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    let res = commit_vote(&dao, MEMBERS[0], 0, Vote::Yes, [1; 32]);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::CommitVote {
+            account: MEMBERS[0].into(),
+            proposal_id: 0,
+        }
+        .encode()
+    )));
+    // must fail since the member has already committed
+    assert!(commit_vote(&dao, MEMBERS[0], 0, Vote::No, [1; 32]).main_failed());
+    assert!(!commit_vote(&dao, MEMBERS[1], 0, Vote::No, [2; 32]).main_failed());
+    // the commitment is never revealed
+    assert!(!commit_vote(&dao, MEMBERS[2], 0, Vote::No, [3; 32]).main_failed());
+    // must fail since the voting period hasn't expired
+    assert!(reveal_vote(&dao, MEMBERS[0], 0, Vote::Yes, [1; 32]).main_failed());
+
+    sys.spend_blocks(1000);
+
+    // must fail since the reveal period hasn't expired
+    assert!(process(&dao, MEMBERS[0], 0).main_failed());
+    // must fail since the vote doesn't match the commitment
+    assert!(reveal_vote(&dao, MEMBERS[1], 0, Vote::Yes, [2; 32]).main_failed());
+    assert!(reveal_vote(&dao, MEMBERS[1], 0, Vote::No, [1; 32]).main_failed());
+
+    let res = reveal_vote(&dao, MEMBERS[0], 0, Vote::Yes, [1; 32]);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitVote {
+            account: MEMBERS[0].into(),
+            proposal_id: 0,
+            vote: Vote::Yes,
+        }
+        .encode()
+    )));
+    // must fail since the vote has been revealed
+    assert!(reveal_vote(&dao, MEMBERS[0], 0, Vote::Yes, [1; 32]).main_failed());
+    assert!(!reveal_vote(&dao, MEMBERS[1], 0, Vote::No, [2; 32]).main_failed());
+
+    sys.spend_blocks(500);
+
+    // must fail since the reveal period has expired
+    assert!(reveal_vote(&dao, MEMBERS[2], 0, Vote::No, [3; 32]).main_failed());
+
+    let res = process(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ProcessProposal {
+            applicant: MEMBERS[3].into(),
+            proposal_id: 0,
+            did_pass: true,
+        }
+        .encode()
+    )));
+
+    // the unrevealed commitment is ignored
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert_eq!(proposal.yes_votes, 1000);
+    assert_eq!(proposal.no_votes, 100);
+    assert_eq!(proposal.vote_commitments.len(), 3);
+}
+
+#[test]
+fn batch_voting() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 100).main_failed());
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 100).main_failed());
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 100).main_failed());
+
+    sys.spend_blocks(100);
+
+    // must fail since there are no votes
+    assert!(submit_votes(&dao, MEMBERS[0], vec![], false).main_failed());
+    // must fail since the proposal doesn't exist
+    let votes = vec![(0, Vote::Yes), (5, Vote::Yes)];
+    assert!(submit_votes(&dao, MEMBERS[0], votes, true).main_failed());
+
+    let votes = vec![(0, Vote::Yes), (1, Vote::No), (0, Vote::No), (7, Vote::Yes)];
+    let res = submit_votes(&dao, MEMBERS[0], votes, false);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitVotes {
+            account: MEMBERS[0].into(),
+            results: vec![
+                (0, VoteResult::Counted { vote: Vote::Yes }),
+                (1, VoteResult::Counted { vote: Vote::No }),
+                (
+                    0,
+                    VoteResult::Rejected {
+                        error: String::from("account has already voted on that proposal"),
+                    },
+                ),
+                (
+                    7,
+                    VoteResult::Rejected {
+                        error: String::from("proposal does not exist"),
+                    },
+                ),
+            ],
+        }
+        .encode()
+    )));
+
+    let votes = vec![(1, Vote::Yes), (0, Vote::No)];
+    assert!(!submit_votes(&dao, MEMBERS[1], votes, true).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = state
+        .proposals
+        .iter()
+        .find(|(id, _)| *id == 0)
+        .expect("Proposal does not exist");
+    assert_eq!((proposal.yes_votes, proposal.no_votes), (1000, 100));
+    let (_, proposal) = state
+        .proposals
+        .iter()
+        .find(|(id, _)| *id == 1)
+        .expect("Proposal does not exist");
+    assert_eq!((proposal.yes_votes, proposal.no_votes), (100, 1000));
+    // the member can't ragequit until the latest proposal voted YES is processed
+    for (member, highest_index_yes_vote) in [(MEMBERS[0], 0), (MEMBERS[1], 1)] {
+        let (_, member) = state
+            .members
+            .iter()
+            .find(|(id, _)| *id == member.into())
+            .expect("Member does not exist");
+        assert_eq!(member.highest_index_yes_vote, Some(highest_index_yes_vote));
+    }
+}
+
+#[test]
+fn vote_gas_does_not_grow_with_voters() {
+    let voters: Vec<u64> = (100..2100).collect();
+    let sys = System::new();
+    init_mock_token_with_accounts(&sys, &voters);
+    init_dao_with_config(
+        &sys,
+        InitDao {
+            voting_period_length: 100_000_000,
+            ..dao_config()
+        },
+    );
+
+    let dao = sys.get_program(2);
+
+    for voter in &voters {
+        assert!(!deposit(&dao, *voter, 1000).main_failed());
+    }
+    assert!(!proposal(&dao, voters[0], MEMBERS[0], 100).main_failed());
+
+    let gas: Vec<Gas> = voters
+        .iter()
+        .map(|voter| {
+            let res = vote(&dao, *voter, 0, Vote::Yes);
+            assert!(!res.main_failed());
+            res.main_gas_burned()
+        })
+        .collect();
+
+    // the votes cast among a hundred and among two thousand voters cost the same
+    let total = |gas: &[Gas]| gas.iter().fold(Gas::zero(), |total, gas| total + *gas);
+    let early = total(&gas[100..110]);
+    let late = total(&gas[1990..2000]);
+    assert!(late * 4u64 <= early * 5u64, "{early} {late}");
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert_eq!(proposal.votes_by_member.len(), voters.len());
+    assert_eq!(proposal.yes_votes, 1000 * voters.len() as u128);
+}
+
+#[test]
+fn signal_proposal_voting() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao(&sys);
+
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+
+    assert!(!approve(&ft, MEMBERS[0], 2, 1000).main_failed());
+    assert!(!approve(&ft, MEMBERS[1], 2, 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+
+    // must fail since account is not a DAO member
+    assert!(signal_proposal(&dao, MEMBERS[2]).main_failed());
+
+    let res = signal_proposal(&dao, MEMBERS[0]);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitSignalProposal {
+            proposer: MEMBERS[0].into(),
+            proposal_id: 0,
+        }
+        .encode()
+    )));
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+    assert!(!vote(&dao, MEMBERS[1], 0, Vote::Yes).main_failed());
+
+    // the YES vote on the signal proposal doesn't lock the shares
+    assert!(!ragequit(&dao, MEMBERS[1], 500).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.locked_funds, 0);
+
+    sys.spend_blocks(1000001);
+
+    let res = process(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ProcessProposal {
+            applicant: ZERO_ID.into(),
+            proposal_id: 0,
+            did_pass: true,
+        }
+        .encode()
+    )));
+    // must fail since there is nothing to execute
+    assert!(execute(&dao, MEMBERS[0], 0).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert!(matches!(proposal.status, ProposalStatus::Executed));
+}
+
+#[test]
+fn choice_proposal_plurality() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao(&sys);
+
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+
+    assert!(!approve(&ft, MEMBERS[0], 2, 1000).main_failed());
+    assert!(!approve(&ft, MEMBERS[1], 2, 2000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 2000).main_failed());
+
+    let options = vec![
+        ChoiceOption {
+            label: "Keep".to_string(),
+            action: None,
+        },
+        ChoiceOption {
+            label: "Change".to_string(),
+            action: Some(ProposalAction::UpdateParameter(
+                DaoParameter::GracePeriodLength(1000),
+            )),
+        },
+    ];
+    // must fail since the proposal has a single option
+    assert!(choice_proposal(
+        &dao,
+        MEMBERS[0],
+        options[..1].to_vec(),
+        TallyMethod::Plurality
+    )
+    .main_failed());
+    assert!(!choice_proposal(&dao, MEMBERS[0], options, TallyMethod::Plurality).main_failed());
+
+    // must fail since choice proposals are voted with ballots
+    assert!(vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+    // must fail since the ranking doesn't match the tally method
+    assert!(ballot(&dao, MEMBERS[0], 0, Ballot::Ranked(vec![0, 1])).main_failed());
+    // must fail since the option does not exist
+    assert!(ballot(&dao, MEMBERS[0], 0, Ballot::Single(2)).main_failed());
+    // must fail since the split weight exceeds the member shares
+    assert!(ballot(&dao, MEMBERS[1], 0, Ballot::Split(vec![(0, 2000), (1, 1)])).main_failed());
+
+    let res = ballot(&dao, MEMBERS[0], 0, Ballot::Single(0));
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitBallot {
+            account: MEMBERS[0].into(),
+            proposal_id: 0,
+        }
+        .encode()
+    )));
+    // must fail since the member has already voted
+    assert!(ballot(&dao, MEMBERS[0], 0, Ballot::Single(1)).main_failed());
+    assert!(!ballot(
+        &dao,
+        MEMBERS[1],
+        0,
+        Ballot::Split(vec![(0, 500), (1, 1000)])
+    )
+    .main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    assert!(!execute(&dao, MEMBERS[0], 0).main_failed());
+
+    // "Keep" wins with 1500 shares against 1000
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert!(proposal.did_pass);
+    assert_eq!(proposal.winning_option, Some(0));
+    assert!(proposal
+        .ballot_receipts
+        .iter()
+        .map(|(voter, receipt)| (*voter, receipt.sequence))
+        .eq([(MEMBERS[0].into(), 0), (MEMBERS[1].into(), 1)]));
+
+    let receipt: Option<BallotReceipt> =
+        read_state_using_wasm(&dao, "ballot_receipt", (0u128, ActorId::from(MEMBERS[1])));
+    assert!(matches!(
+        receipt,
+        Some(BallotReceipt {
+            ballot: Ballot::Split(split),
+            weight: 1500,
+            ..
+        }) if split == vec![(0, 500), (1, 1000)]
+    ));
+    let receipt: Option<BallotReceipt> =
+        read_state_using_wasm(&dao, "ballot_receipt", (0u128, ActorId::from(MEMBERS[2])));
+    assert!(receipt.is_none());
+    // the winning option has no action
+    assert!(proposal.action_statuses.is_empty());
+}
+
+#[test]
+fn choice_proposal_instant_runoff() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao(&sys);
+
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+
+    for (member, amount) in MEMBERS.iter().zip([1000, 2000, 3000, 4000]) {
+        assert!(!approve(&ft, *member, 2, amount).main_failed());
+        assert!(!deposit(&dao, *member, amount).main_failed());
+    }
+
+    let options = vec![
+        ChoiceOption {
+            label: "A".to_string(),
+            action: None,
+        },
+        ChoiceOption {
+            label: "B".to_string(),
+            action: Some(ProposalAction::Transfer {
+                recipient: MEMBERS[1].into(),
+                amount: 300,
+            }),
+        },
+        ChoiceOption {
+            label: "C".to_string(),
+            action: Some(ProposalAction::Transfer {
+                recipient: MEMBERS[2].into(),
+                amount: 500,
+            }),
+        },
+    ];
+    let res = choice_proposal(&dao, MEMBERS[0], options, TallyMethod::InstantRunoff);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::SubmitChoiceProposal {
+            proposer: MEMBERS[0].into(),
+            proposal_id: 0,
+            amount: 500,
+        }
+        .encode()
+    )));
+
+    // must fail since the single option doesn't match the tally method
+    assert!(ballot(&dao, MEMBERS[0], 0, Ballot::Single(0)).main_failed());
+    // must fail since the ranking repeats the option
+    assert!(ballot(&dao, MEMBERS[0], 0, Ballot::Ranked(vec![2, 2])).main_failed());
+
+    // A and C get 4000 shares in the first round, B is eliminated and its ballot moves to C
+    assert!(!ballot(&dao, MEMBERS[0], 0, Ballot::Ranked(vec![2, 1])).main_failed());
+    assert!(!ballot(&dao, MEMBERS[1], 0, Ballot::Ranked(vec![1, 2])).main_failed());
+    assert!(!ballot(&dao, MEMBERS[2], 0, Ballot::Ranked(vec![2, 1])).main_failed());
+    assert!(!ballot(&dao, MEMBERS[3], 0, Ballot::Ranked(vec![0])).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    let res = execute(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecuteProposal { proposal_id: 0 }.encode()
+    )));
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    assert_eq!(proposal.winning_option, Some(2));
+    assert_eq!(state.locked_funds, 0);
+    assert_eq!(state.balance, 9500);
+}
+
+#[test]
+fn quadratic_voting() {
+    // the same votes pass in the quadratic mode and fail in the linear mode
+    for (voting_mode, did_pass) in [(VotingMode::Linear, false), (VotingMode::Quadratic, true)] {
+        let sys = System::new();
+        init_mock_token(&sys);
+        init_dao_with_voting_mode(&sys, voting_mode);
+
+        let dao = sys.get_program(2);
+
+        // the whale has 8100 shares (weight 90), the rest of members have 1000 shares (weight 31)
+        assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+        assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+        assert!(!deposit(&dao, MEMBERS[2], 1000).main_failed());
+        assert!(!deposit(&dao, MEMBERS[3], 8100).main_failed());
+
+        assert!(!proposal_with_quorum(&dao, MEMBERS[0], MEMBERS[2], 500, 40).main_failed());
+        assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+        assert!(!vote(&dao, MEMBERS[1], 0, Vote::Yes).main_failed());
+        assert!(!vote(&dao, MEMBERS[2], 0, Vote::Yes).main_failed());
+        assert!(!vote(&dao, MEMBERS[3], 0, Vote::No).main_failed());
+
+        let state: DaoState = dao.read_state().expect("Unable to read the state");
+        let (_, proposal) = &state.proposals[0];
+        match voting_mode {
+            VotingMode::Linear => {
+                assert_eq!((proposal.yes_votes, proposal.no_votes), (3000, 8100));
+            }
+            VotingMode::Quadratic => {
+                assert_eq!((proposal.yes_votes, proposal.no_votes), (93, 90));
+            }
+        }
+
+        sys.spend_blocks(1000001);
+
+        let res = process(&dao, MEMBERS[0], 0);
+        assert!(res.contains(&(
+            MEMBERS[0],
+            DaoEvent::ProcessProposal {
+                applicant: MEMBERS[2].into(),
+                proposal_id: 0,
+                did_pass,
+            }
+            .encode()
+        )));
+    }
+}
+
+#[test]
+fn voting_mode_change_with_open_proposals() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 900).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 100).main_failed());
+
+    let actions = vec![ProposalAction::UpdateParameter(DaoParameter::VotingMode(
+        VotingMode::Quadratic,
+    ))];
+    assert!(!batch_proposal(&dao, MEMBERS[0], actions, true).main_failed());
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+    assert!(!proposal_with_quorum(&dao, MEMBERS[0], MEMBERS[2], 100, 50).main_failed());
+
+    // the period between the proposals
+    sys.spend_blocks(101);
+    assert!(!vote(&dao, MEMBERS[1], 1, Vote::Yes).main_failed());
+
+    // the voting period of the first proposal
+    sys.spend_blocks(900);
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    assert!(!execute(&dao, MEMBERS[0], 0).main_failed());
+
+    // the open proposal keeps weighing the votes linearly
+    assert!(!vote(&dao, MEMBERS[0], 1, Vote::No).main_failed());
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert!(matches!(state.voting_mode, VotingMode::Quadratic));
+    let (_, open_proposal) = state
+        .proposals
+        .iter()
+        .find(|(proposal_id, _)| *proposal_id == 1)
+        .expect("Proposal does not exist");
+    assert!(matches!(open_proposal.voting_mode, VotingMode::Linear));
+    assert_eq!(
+        (open_proposal.yes_votes, open_proposal.no_votes),
+        (100, 900)
+    );
+
+    sys.spend_blocks(1000001);
+
+    // the quorum is counted against the linear voting power of 1000 shares
+    let res = process(&dao, MEMBERS[0], 1);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ProcessProposal {
+            applicant: MEMBERS[2].into(),
+            proposal_id: 1,
+            did_pass: false,
+        }
+        .encode()
+    )));
+
+    // the new proposal is weighed quadratically
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 100).main_failed());
+    assert!(!vote(&dao, MEMBERS[0], 2, Vote::Yes).main_failed());
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, new_proposal) = state
+        .proposals
+        .iter()
+        .find(|(proposal_id, _)| *proposal_id == 2)
+        .expect("Proposal does not exist");
+    assert!(matches!(new_proposal.voting_mode, VotingMode::Quadratic));
+    assert_eq!(new_proposal.yes_votes, 30);
+}
+
+#[test]
+fn quadratic_split_ballot() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao_with_voting_mode(&sys, VotingMode::Quadratic);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 10000).main_failed());
+
+    let options = vec![
+        ChoiceOption {
+            label: "Keep".to_string(),
+            action: None,
+        },
+        ChoiceOption {
+            label: "Change".to_string(),
+            action: Some(ProposalAction::UpdateParameter(
+                DaoParameter::GracePeriodLength(1000),
+            )),
+        },
+    ];
+    assert!(!choice_proposal(&dao, MEMBERS[0], options, TallyMethod::Plurality).main_failed());
+    assert!(!ballot(
+        &dao,
+        MEMBERS[0],
+        0,
+        Ballot::Split(vec![(0, 5000), (1, 5000)])
+    )
+    .main_failed());
+
+    // the weight of 10000 shares is 100, the split doesn't raise it to 70 + 70
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let (_, proposal) = &state.proposals[0];
+    let (_, receipt) = &proposal.ballot_receipts[0];
+    assert_eq!(receipt.weight, 100);
+    assert!(matches!(&receipt.ballot, Ballot::Split(split) if split == &vec![(0, 50), (1, 50)]));
+}
+
+#[test]
+fn proposal_fails_without_voting_power() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!optimistic_proposal(&dao, MEMBERS[0], MEMBERS[2], 100).main_failed());
+    // the only member leaves the DAO before the proposal is processed
+    assert!(!ragequit(&dao, MEMBERS[0], 1000).main_failed());
+
+    sys.spend_blocks(1100001);
+
+    let res = process(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ProcessProposal {
+            applicant: MEMBERS[2].into(),
+            proposal_id: 0,
+            did_pass: false,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn timelock_and_veto() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao_with_timelock(&sys, 100000);
+
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+
+    assert!(!approve(&ft, MEMBERS[0], 2, 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+
+    for proposal_id in 0..2 {
+        assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 400).main_failed());
+        assert!(!vote(&dao, MEMBERS[0], proposal_id, Vote::Yes).main_failed());
+    }
+
+    sys.spend_blocks(1000001);
+
+    // must fail since the proposal is not processed yet
+    assert!(execute(&dao, MEMBERS[0], 0).main_failed());
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    assert!(!process(&dao, MEMBERS[0], 1).main_failed());
+
+    // must fail since the timelock has not expired
+    assert!(execute(&dao, MEMBERS[0], 0).main_failed());
+    // must fail since only the guardian can veto proposals
+    assert!(veto(&dao, MEMBERS[0], 1).main_failed());
+
+    let res = veto(&dao, GUARDIAN_ID, 1);
+    assert!(res.contains(&(
+        GUARDIAN_ID,
+        DaoEvent::VetoProposal { proposal_id: 1 }.encode()
+    )));
+    // must fail since the proposal is vetoed
+    assert!(veto(&dao, GUARDIAN_ID, 1).main_failed());
+
+    sys.spend_blocks(100001);
+
+    // must fail since the timelock has expired
+    assert!(veto(&dao, GUARDIAN_ID, 0).main_failed());
+    // must fail since the proposal is vetoed
+    assert!(execute(&dao, MEMBERS[0], 1).main_failed());
+
+    let res = execute(&dao, MEMBERS[0], 0);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::ExecuteProposal { proposal_id: 0 }.encode()
+    )));
+    // must fail since the proposal has already been executed
+    assert!(execute(&dao, MEMBERS[0], 0).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.locked_funds, 0);
+    assert_eq!(state.balance, 600);
+}
+
+#[test]
+fn pause_actions() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao_with_guardians(&sys, 0, GUARDIANS, 2);
+
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+
+    assert!(!approve(&ft, MEMBERS[0], 2, 2000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+
+    let actions = vec![
+        PausableAction::Deposit,
+        PausableAction::SubmitFundingProposal,
+    ];
+    // must fail since only guardians can pause actions
+    assert!(pause(&dao, MEMBERS[0], actions.clone()).main_failed());
+
+    let res = pause(&dao, GUARDIANS[0], actions.clone());
+    assert!(res.contains(&(
+        GUARDIANS[0],
+        DaoEvent::GuardianApproval {
+            guardian: GUARDIANS[0].into(),
+            action: GuardianAction::Pause(actions.clone()),
+            approvals: 1,
+        }
+        .encode()
+    )));
+    // the action is not paused until the threshold is reached
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 100).main_failed());
+
+    // the approval counts whatever order the actions are listed in
+    let res = pause(
+        &dao,
+        GUARDIANS[1],
+        vec![
+            PausableAction::SubmitFundingProposal,
+            PausableAction::Deposit,
+            PausableAction::SubmitFundingProposal,
+        ],
+    );
+    assert!(res.contains(&(
+        GUARDIANS[1],
+        DaoEvent::Pause {
+            actions: actions.clone()
+        }
+        .encode()
+    )));
+
+    // must fail since the actions are paused
+    assert!(deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(proposal(&dao, MEMBERS[0], MEMBERS[2], 100).main_failed());
+    // the rest of the actions are available
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    assert!(!unpause(&dao, GUARDIANS[2], actions.clone()).main_failed());
+    assert!(!unpause(&dao, GUARDIANS[0], actions).main_failed());
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+}
+
+#[test]
+fn revoke_guardian_approval() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao_with_guardians(&sys, 0, GUARDIANS, 2);
+
+    let dao = sys.get_program(2);
+
+    let actions = vec![PausableAction::Deposit];
+    assert!(!pause(&dao, GUARDIANS[0], actions.clone()).main_failed());
+
+    let revoke = |guardian: u64| {
+        dao.send(
+            guardian,
+            DaoAction::RevokeGuardianApproval {
+                action: GuardianAction::Pause(actions.clone()),
+            },
+        )
+    };
+    // must fail since the guardian hasn't approved the action
+    assert!(revoke(GUARDIANS[1]).main_failed());
+    // must fail since only guardians can revoke approvals
+    assert!(revoke(MEMBERS[0]).main_failed());
+
+    let res = revoke(GUARDIANS[0]);
+    assert!(res.contains(&(
+        GUARDIANS[0],
+        DaoEvent::RevokeGuardianApproval {
+            guardian: GUARDIANS[0].into(),
+            action: GuardianAction::Pause(actions.clone()),
+            approvals: 0,
+        }
+        .encode()
+    )));
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert!(state.guardian_approvals.is_empty());
+
+    // the revoked approval doesn't count towards the threshold
+    let res = pause(&dao, GUARDIANS[1], actions.clone());
+    assert!(res.contains(&(
+        GUARDIANS[1],
+        DaoEvent::GuardianApproval {
+            guardian: GUARDIANS[1].into(),
+            action: GuardianAction::Pause(actions.clone()),
+            approvals: 1,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn ragequit_during_pause() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao(&sys);
+
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+
+    assert!(!approve(&ft, MEMBERS[0], 2, 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 500).main_failed());
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+
+    let actions = vec![
+        PausableAction::Deposit,
+        PausableAction::SubmitFundingProposal,
+        PausableAction::SubmitExecuteProposal,
+        PausableAction::SubmitBatchProposal,
+        PausableAction::ExecuteProposal,
+        PausableAction::SubmitVote,
+        PausableAction::ResumeExecution,
+    ];
+    assert!(!pause(&dao, GUARDIAN_ID, actions).main_failed());
+
+    // must fail since the proposal the member voted YES isn't processed
+    assert!(ragequit(&dao, MEMBERS[0], 500).main_failed());
+
+    // the proposal processing can't be paused, so the member exits once the proposal is processed
+    sys.spend_blocks(1000001);
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    let res = ragequit(&dao, MEMBERS[0], 500);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::RageQuit {
+            member: MEMBERS[0].into(),
+            amount: 500,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn preview_ragequit_payout() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+
+    let preview: u128 = read_state_using_wasm(
+        &dao,
+        "preview_ragequit",
+        (ActorId::from(MEMBERS[0]), 500u128),
+    );
+    assert_eq!(preview, 500);
+    // the preview matches the payout while the observed balance is up to date
+    assert!(!ragequit_with_min_funds(&dao, MEMBERS[0], 500, preview).main_failed());
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.balance, 1500);
+
+    // the transfer DAO hasn't observed yet isn't included in the preview
+    assert!(!ft
+        .send(
+            MEMBERS[2],
+            FTAction::Transfer {
+                from: MEMBERS[2].into(),
+                to: 2.into(),
+                amount: 1500,
+            },
+        )
+        .main_failed());
+    let preview: u128 = read_state_using_wasm(
+        &dao,
+        "preview_ragequit",
+        (ActorId::from(MEMBERS[0]), 500u128),
+    );
+    assert_eq!(preview, 500);
+    // the ragequit refreshes the balance, so the member receives 1000 tokens
+    assert!(!ragequit_with_min_funds(&dao, MEMBERS[0], 500, preview).main_failed());
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.balance, 2000);
+
+    // the preview is 0 for the staked shares that can't be withdrawn
+    assert!(!conviction_proposal(&dao, MEMBERS[1], MEMBERS[2], 100).main_failed());
+    assert!(!stake_conviction(&dao, MEMBERS[1], 0, 600).main_failed());
+    let preview: u128 = read_state_using_wasm(
+        &dao,
+        "preview_ragequit",
+        (ActorId::from(MEMBERS[1]), 500u128),
+    );
+    assert_eq!(preview, 0);
+    assert!(ragequit(&dao, MEMBERS[1], 500).main_failed());
+    let preview: u128 = read_state_using_wasm(
+        &dao,
+        "preview_ragequit",
+        (ActorId::from(MEMBERS[1]), 400u128),
+    );
+    assert_eq!(preview, 800);
+
+    // the preview is 0 until the proposal the member voted YES on is processed
+    assert!(!proposal(&dao, MEMBERS[1], MEMBERS[2], 100).main_failed());
+    assert!(!vote(&dao, MEMBERS[1], 0, Vote::Yes).main_failed());
+    let preview: u128 = read_state_using_wasm(
+        &dao,
+        "preview_ragequit",
+        (ActorId::from(MEMBERS[1]), 400u128),
+    );
+    assert_eq!(preview, 0);
+    assert!(ragequit(&dao, MEMBERS[1], 400).main_failed());
+}
+
+#[test]
+fn ragequit_dao() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao(&sys);
+
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+
+    assert!(!approve(&ft, MEMBERS[1], 2, 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+
+    let res = ragequit(&dao, MEMBERS[1], 800);
+    assert!(res.contains(&(
+        MEMBERS[1],
+        DaoEvent::RageQuit {
+            member: MEMBERS[1].into(),
+            amount: 800,
+        }
+        .encode()
+    )));
+
+    assert!(!approve(&ft, MEMBERS[0], 2, 1000).main_failed());
+    assert!(!approve(&ft, MEMBERS[1], 2, 1000).main_failed());
+    assert!(!approve(&ft, MEMBERS[2], 2, 1000).main_failed());
+    assert!(!approve(&ft, MEMBERS[3], 2, 1000).main_failed());
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[2], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[3], 1000).main_failed());
+
+    //submit funding proposal
+    assert!(!proposal(&dao, MEMBERS[1], MEMBERS[2], 800).main_failed());
+
+    // votes YES
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+    assert!(!vote(&dao, MEMBERS[1], 0, Vote::Yes).main_failed());
+    assert!(!vote(&dao, MEMBERS[2], 0, Vote::Yes).main_failed());
+    assert!(!vote(&dao, MEMBERS[3], 0, Vote::Yes).main_failed());
+
+    sys.spend_blocks(1000001);
+
+    assert!(!process(&dao, MEMBERS[0], 0).main_failed());
+    assert!(!execute(&dao, MEMBERS[0], 0).main_failed());
+
+    let res = ragequit(&dao, MEMBERS[1], 800);
+    assert!(res.contains(&(
+        MEMBERS[1],
+        DaoEvent::RageQuit {
+            member: MEMBERS[1].into(),
+            amount: 647,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn ragequit_failures() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao(&sys);
+
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+
+    assert!(!approve(&ft, MEMBERS[0], 2, 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    // must fail since the account is not a DAO member
+    assert!(ragequit(&dao, MEMBERS[1], 800).main_failed());
+    // must fail since the account has no sufficient shares
+    assert!(ragequit(&dao, MEMBERS[0], 1100).main_failed());
+    // must fail since the member would receive less than expected
+    assert!(ragequit_with_min_funds(&dao, MEMBERS[0], 100, 101).main_failed());
+    //submit funding proposal
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 800).main_failed());
+    assert!(!vote(&dao, MEMBERS[0], 0, Vote::Yes).main_failed());
+    // must fail since cant ragequit until highest index proposal member voted YES on is processed
+    assert!(ragequit(&dao, MEMBERS[0], 100).main_failed());
+}
+
+#[test]
+fn ragequit_failed_transfer() {
+    let sys = System::new();
+    let fail_transfers = init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+
+    fail_transfers.set(true);
+    let res = ragequit(&dao, MEMBERS[0], 1000);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::TransferFailed {
+            recipient: MEMBERS[0].into(),
+            amount: 1000,
+        }
+        .encode()
+    )));
+
+    // the shares are left intact
+    fail_transfers.set(false);
+    let res = ragequit(&dao, MEMBERS[0], 1000);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::RageQuit {
+            member: MEMBERS[0].into(),
+            amount: 1000,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn continue_transaction_failures() {
+    let sys = System::new();
+    init_fungible_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    // must fail since the deposit transaction has been already settled
+    assert!(continue_transaction(&dao, MEMBERS[0], 0).main_failed());
+    // must fail since the transaction does not exist
+    assert!(continue_transaction(&dao, MEMBERS[0], 1).main_failed());
+}
+
+#[test]
+fn continue_transaction_with_lost_reply() {
+    // the transfer takes place and the transaction is completed,
+    // or it doesn't and the transfer is sent again
+    for transferred in [true, false] {
+        let sys = System::new();
+        let (fail_transfers, lose_replies) = init_mock_token_with_lost_replies(&sys);
+        init_dao(&sys);
+
+        let dao = sys.get_program(2);
+
+        fail_transfers.set(!transferred);
+        lose_replies.set(true);
+        assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+        // must fail since the deposit is waiting for the reply
+        assert!(deposit(&dao, MEMBERS[1], 1000).main_failed());
+
+        fail_transfers.set(false);
+        lose_replies.set(false);
+        assert!(!continue_transaction(&dao, MEMBERS[1], 0).main_failed());
+
+        let state: DaoState = dao.read_state().expect("Unable to read the state");
+        assert!(state.transactions.is_empty());
+        assert_eq!(state.total_shares, 1000);
+        assert_eq!(state.balance, 1000);
+        assert!(state.is_member(&MEMBERS[0].into()));
+    }
+}
+
+#[test]
+fn ragequit_with_pending_transaction() {
+    let sys = System::new();
+    let (_, lose_replies) = init_mock_token_with_lost_replies(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    lose_replies.set(true);
+    assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+    lose_replies.set(false);
+
+    // the exit is queued while the deposit waits for the reply
+    let res = ragequit(&dao, MEMBERS[0], 400);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::RageQuitQueued {
+            member: MEMBERS[0].into(),
+            amount: 400,
+        }
+        .encode()
+    )));
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.total_shares, 600);
+    assert_eq!(state.queued_exits, vec![(MEMBERS[0].into(), 400)]);
+    // must fail since the deposit is still pending
+    assert!(claim_exit(&dao, MEMBERS[0], 0).main_failed());
+
+    assert!(!continue_transaction(&dao, MEMBERS[1], 1).main_failed());
+    // must fail since the account has no queued exit
+    assert!(claim_exit(&dao, MEMBERS[1], 0).main_failed());
+    // must fail since the queued shares are worth 400 tokens
+    assert!(claim_exit(&dao, MEMBERS[0], 401).main_failed());
+    let res = claim_exit(&dao, MEMBERS[0], 400);
+    assert!(res.contains(&(
+        MEMBERS[0],
+        DaoEvent::RageQuit {
+            member: MEMBERS[0].into(),
+            amount: 400,
+        }
+        .encode()
+    )));
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert!(state.transactions.is_empty());
+    assert!(state.queued_exits.is_empty());
+    assert_eq!(state.balance, 1600);
+    assert_eq!(state.total_shares, 1600);
+}
+
+#[test]
+fn deposit_with_transfer_fee() {
+    let sys = System::new();
+    let transfer_fee = init_mock_token_with_transfer_fee(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+
+    // must fail since the DAO receives 990 tokens for the deposit of 1000 tokens
+    transfer_fee.set(10);
+    assert!(deposit(&dao, MEMBERS[1], 1000).main_failed());
+    transfer_fee.set(0);
+    // must fail since the balance didn't change by the deposit amount
+    assert!(continue_transaction(&dao, MEMBERS[1], 1).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert_eq!(state.transactions.len(), 1);
+    assert_eq!(state.total_shares, 1000);
+    assert!(!state.is_member(&MEMBERS[1].into()));
+}
+
+#[test]
+fn continue_transaction_with_other_transfers() {
+    let sys = System::new();
+    let (_, lose_replies) = init_mock_token_with_lost_replies(&sys);
+    init_dao(&sys);
+
+    let ft = sys.get_program(1);
+    let dao = sys.get_program(2);
+
+    lose_replies.set(true);
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    lose_replies.set(false);
+
+    // the transfer of a third party reaches the DAO balance
+    assert!(!ft
+        .send(
+            MEMBERS[1],
+            FTAction::Transfer {
+                from: MEMBERS[1].into(),
+                to: 2.into(),
+                amount: 1000,
+            },
+        )
+        .main_failed());
+    // must fail since the balance doesn't show whether the deposit has taken place
+    assert!(continue_transaction(&dao, MEMBERS[1], 0).main_failed());
+    // the deposit blocks other token transactions until it is settled
+    assert!(deposit(&dao, MEMBERS[1], 500).main_failed());
+
+    // must fail since only guardians settle transactions
+    assert!(settle_transaction(&dao, MEMBERS[1], 0, true).main_failed());
+    // the guardian checked the token contract and completes the deposit
+    assert!(!settle_transaction(&dao, GUARDIAN_ID, 0, true).main_failed());
+    // must fail since the transaction has been settled
+    assert!(settle_transaction(&dao, GUARDIAN_ID, 0, true).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert!(state.transactions.is_empty());
+    assert_eq!(state.balance, 2000);
+    assert_eq!(state.total_shares, 1000);
+
+    // the DAO accepts token transactions again
+    assert!(!deposit(&dao, MEMBERS[1], 500).main_failed());
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    assert!(state.transactions.is_empty());
+    assert_eq!(state.total_shares, 1250);
+}
+
+#[test]
+fn non_panicking_state_queries() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 500).main_failed());
+    assert!(!proposal(&dao, MEMBERS[0], MEMBERS[2], 100).main_failed());
+    // the member without shares isn't a member anymore
+    assert!(!ragequit(&dao, MEMBERS[1], 500).main_failed());
+
+    let proposal: Option<Proposal> = read_state_using_wasm(&dao, "proposal_info_v2", 0u128);
+    assert!(matches!(proposal, Some(proposal) if proposal.amount == 100));
+    let proposal: Option<Proposal> = read_state_using_wasm(&dao, "proposal_info_v2", 1u128);
+    assert!(proposal.is_none());
+
+    let member: Option<Member> =
+        read_state_using_wasm(&dao, "member_info_v2", ActorId::from(MEMBERS[0]));
+    assert!(matches!(member, Some(member) if member.shares == 1000));
+    for account in [MEMBERS[1], MEMBERS[2]] {
+        let member: Option<Member> =
+            read_state_using_wasm(&dao, "member_info_v2", ActorId::from(account));
+        assert!(member.is_none());
+    }
+
+    for (account, expected) in [(MEMBERS[0], 1000), (MEMBERS[1], 0), (MEMBERS[2], 0)] {
+        let power: u128 = read_state_using_wasm(&dao, "member_power_v2", ActorId::from(account));
+        assert_eq!(power, expected);
+    }
+}
+
+#[test]
+fn state_queries() {
+    let sys = System::new();
+    init_mock_token(&sys);
+    init_dao(&sys);
+
+    let dao = sys.get_program(2);
+
+    assert!(!deposit(&dao, MEMBERS[0], 1000).main_failed());
+    assert!(!deposit(&dao, MEMBERS[1], 1000).main_failed());
+    for member in [MEMBERS[0], MEMBERS[1], MEMBERS[1], MEMBERS[1]] {
+        assert!(!proposal(&dao, member, MEMBERS[2], 100).main_failed());
+    }
+    assert!(!vote(&dao, MEMBERS[0], 2, Vote::Yes).main_failed());
+
+    let state: DaoState = dao.read_state().expect("Unable to read the state");
+    let proposal = |id: u128| {
+        let (_, proposal) = state
+            .proposals
+            .iter()
+            .find(|(proposal_id, _)| *proposal_id == id)
+            .unwrap();
+        (id, proposal.clone().preview())
+    };
+
+    // the second page of the proposals of MEMBERS[1], the votes aren't encoded
+    let res = query_state(
+        &dao,
+        MEMBERS[2],
+        StateQuery::Proposals {
+            filter: ProposalFilter {
+                proposer: Some(MEMBERS[1].into()),
+                ..Default::default()
+            },
+            offset: 1,
+            limit: 1,
+        },
+    );
+    assert!(res.contains(&(
+        MEMBERS[2],
+        DaoEvent::QueryState {
+            reply: StateReply::Proposals(vec![proposal(2)]),
+        }
+        .encode()
+    )));
+
+    let res = query_state(
+        &dao,
+        MEMBERS[2],
+        StateQuery::Members {
+            offset: 1,
+            limit: 5,
+        },
+    );
+    let (_, member) = state
+        .members
+        .iter()
+        .find(|(account, _)| *account == MEMBERS[1].into())
+        .unwrap();
+    assert!(res.contains(&(
+        MEMBERS[2],
+        DaoEvent::QueryState {
+            reply: StateReply::Members(vec![(MEMBERS[1].into(), member.clone())]),
+        }
+        .encode()
+    )));
+
+    let res = query_state(&dao, MEMBERS[2], StateQuery::Summary);
+    assert!(res.contains(&(
+        MEMBERS[2],
+        DaoEvent::QueryState {
+            reply: StateReply::Summary(DaoSummary {
+                member_count: 2,
+                total_shares: 2000,
+                balance: 2000,
+                locked_funds: 400,
+                proposal_count: 4,
+                submitted_proposals: 4,
+                ..Default::default()
+            }),
+        }
+        .encode()
+    )));
 }
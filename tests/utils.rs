@@ -1,9 +1,21 @@
 use dao_light_io::*;
 use ft_io::*;
-use gtest::{Program, RunResult, System};
+use gstd::{ActorId, Decode, Encode};
+use gtest::{Program, RunResult, System, WasmProgram};
+use std::{cell::Cell, collections::HashMap, rc::Rc};
 
 pub const MEMBERS: &[u64] = &[3, 4, 5, 6];
 pub const ZERO_ID: u64 = 0;
+pub const ECHO_ID: u64 = 10;
+pub const GUARDIAN_ID: u64 = 11;
+pub const GUARDIANS: &[u64] = &[11, 12, 13];
+pub const STATE_WASM: &str = "target/wasm32-unknown-unknown/debug/dao_light_state.meta.wasm";
+pub const CONVICTION_CONFIG: ConvictionConfig = ConvictionConfig {
+    period: 10000,
+    decay: 9_000_000,
+    max_ratio: 2_000_000,
+    weight: 25_000,
+};
 
 pub fn init_fungible_token(sys: &System) {
     sys.init_logger();
@@ -28,23 +40,242 @@ pub fn init_fungible_token(sys: &System) {
     });
 }
 
-pub fn init_dao(sys: &System) {
+/// Fungible token mock whose transfers can be switched to fail
+#[derive(Debug)]
+pub struct MockToken {
+    balances: HashMap<ActorId, u128>,
+    fail_transfers: Rc<Cell<bool>>,
+    lose_replies: Rc<Cell<bool>>,
+    transfer_fee: Rc<Cell<u128>>,
+}
+
+impl WasmProgram for MockToken {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let action =
+            FTAction::decode(&mut &payload[..]).map_err(|_| "Unable to decode FTAction")?;
+        let event = match action {
+            FTAction::Transfer { from, to, amount } => {
+                if self.fail_transfers.get() {
+                    if self.lose_replies.get() {
+                        return Ok(None);
+                    }
+                    return Err("Transfer failed");
+                }
+                let from_balance = self.balances.entry(from).or_default();
+                if *from_balance < amount {
+                    return Err("Insufficient balance");
+                }
+                *from_balance -= amount;
+                *self.balances.entry(to).or_default() += amount - self.transfer_fee.get();
+                if self.lose_replies.get() {
+                    return Ok(None);
+                }
+                FTEvent::Transfer { from, to, amount }
+            }
+            FTAction::BalanceOf(account) => {
+                FTEvent::Balance(self.balances.get(&account).copied().unwrap_or_default())
+            }
+            _ => return Err("Unsupported action"),
+        };
+        Ok(Some(event.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Err("State is not supported")
+    }
+}
+
+/// Initializes the token mock and returns the switch making its transfers fail
+pub fn init_mock_token(sys: &System) -> Rc<Cell<bool>> {
+    init_mock_token_with_accounts(sys, &[])
+}
+
+/// Initializes the token mock funding `accounts` in addition to the members
+pub fn init_mock_token_with_accounts(sys: &System, accounts: &[u64]) -> Rc<Cell<bool>> {
+    init_mock_token_with_switches(sys, accounts).fail_transfers
+}
+
+/// Initializes the token mock and returns the switches making its transfers fail
+/// and making it not reply to the transfers
+pub fn init_mock_token_with_lost_replies(sys: &System) -> (Rc<Cell<bool>>, Rc<Cell<bool>>) {
+    let switches = init_mock_token_with_switches(sys, &[]);
+    (switches.fail_transfers, switches.lose_replies)
+}
+
+/// Initializes the token mock and returns the fee it withholds from the transferred amount
+pub fn init_mock_token_with_transfer_fee(sys: &System) -> Rc<Cell<u128>> {
+    init_mock_token_with_switches(sys, &[]).transfer_fee
+}
+
+// the switches changing the behavior of the token mock during the test
+struct MockTokenSwitches {
+    fail_transfers: Rc<Cell<bool>>,
+    lose_replies: Rc<Cell<bool>>,
+    transfer_fee: Rc<Cell<u128>>,
+}
+
+fn init_mock_token_with_switches(sys: &System, accounts: &[u64]) -> MockTokenSwitches {
     sys.init_logger();
-    let dao = Program::current(sys);
-    let res = dao.send(
-        MEMBERS[0],
+    let fail_transfers = Rc::new(Cell::new(false));
+    let lose_replies = Rc::new(Cell::new(false));
+    let transfer_fee = Rc::new(Cell::new(0));
+    let ft = Program::mock(
+        sys,
+        MockToken {
+            balances: MEMBERS
+                .iter()
+                .chain(accounts)
+                .map(|member| ActorId::from(*member))
+                .chain(
+                    [
+                        sr25519_signer().public.to_bytes(),
+                        ed25519_signer().verifying_key().to_bytes(),
+                    ]
+                    .map(ActorId::from),
+                )
+                .map(|account| (account, 10000000))
+                .collect(),
+            fail_transfers: fail_transfers.clone(),
+            lose_replies: lose_replies.clone(),
+            transfer_fee: transfer_fee.clone(),
+        },
+    );
+    assert!(!ft.send_bytes(MEMBERS[0], b"init").main_failed());
+    MockTokenSwitches {
+        fail_transfers,
+        lose_replies,
+        transfer_fee,
+    }
+}
+
+/// Program replying with the payload it received
+#[derive(Debug)]
+pub struct EchoProgram;
+
+impl WasmProgram for EchoProgram {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(Some(payload))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Err("State is not supported")
+    }
+}
+
+pub fn init_echo_program(sys: &System) {
+    let echo = Program::mock_with_id(sys, ECHO_ID, EchoProgram);
+    assert!(!echo.send_bytes(MEMBERS[0], b"init").main_failed());
+}
+
+pub fn init_dao(sys: &System) {
+    init_dao_with_timelock(sys, 0);
+}
+
+pub fn init_dao_with_timelock(sys: &System, timelock: u64) {
+    init_dao_with_guardians(sys, timelock, &[GUARDIAN_ID], 1);
+}
+
+pub fn init_dao_with_voting_mode(sys: &System, voting_mode: VotingMode) {
+    init_dao_with_config(
+        sys,
+        InitDao {
+            voting_mode,
+            ..dao_config()
+        },
+    );
+}
+
+pub fn init_dao_with_reveal_period(sys: &System, reveal_period_length: u64) {
+    init_dao_with_config(
+        sys,
+        InitDao {
+            reveal_period_length,
+            ..dao_config()
+        },
+    );
+}
+
+pub fn init_dao_with_guardians(
+    sys: &System,
+    timelock: u64,
+    guardians: &[u64],
+    guardian_threshold: u32,
+) {
+    init_dao_with_config(
+        sys,
         InitDao {
-            approved_token_program_id: 1.into(),
-            period_duration: 100000,
-            grace_period_length: 100000,
-            voting_period_length: 1000000,
+            timelock,
+            guardians: guardians
+                .iter()
+                .map(|guardian| (*guardian).into())
+                .collect(),
+            guardian_threshold,
+            ..dao_config()
         },
     );
+}
+
+/// The DAO config used by the tests unless they override its fields
+pub fn dao_config() -> InitDao {
+    InitDao {
+        approved_token_program_id: 1.into(),
+        period_duration: 100000,
+        grace_period_length: 100000,
+        voting_period_length: 1000000,
+        reveal_period_length: 0,
+        dilution_bound: 3,
+        voting_mode: VotingMode::Linear,
+        conviction_config: CONVICTION_CONFIG,
+        optimistic_cap: 500,
+        objection_threshold: 20,
+        timelock: 0,
+        guardians: vec![GUARDIAN_ID.into()],
+        guardian_threshold: 1,
+    }
+}
+
+pub fn init_dao_with_config(sys: &System, config: InitDao) {
+    sys.init_logger();
+    let dao = Program::current(sys);
+    let res = dao.send(MEMBERS[0], config);
     assert!(!res.main_failed());
 }
 
 pub fn deposit(dao: &Program, member: u64, amount: u128) -> RunResult {
-    dao.send(member, DaoAction::Deposit { amount })
+    deposit_with_min_shares(dao, member, amount, 0)
+}
+
+pub fn deposit_with_min_shares(
+    dao: &Program,
+    member: u64,
+    amount: u128,
+    min_shares: u128,
+) -> RunResult {
+    dao.send(member, DaoAction::Deposit { amount, min_shares })
 }
 
 pub fn approve(ft: &Program, member: u64, to: u64, amount: u128) -> RunResult {
@@ -57,26 +288,434 @@ pub fn approve(ft: &Program, member: u64, to: u64, amount: u128) -> RunResult {
     )
 }
 
+pub fn metadata(title: &str) -> ProposalMetadata {
+    ProposalMetadata {
+        title: title.to_string(),
+        summary: "Summary".to_string(),
+        uri: "ipfs://proposal".to_string(),
+        content_hash: [1; 32],
+    }
+}
+
 pub fn proposal(dao: &Program, member: u64, applicant: u64, amount: u128) -> RunResult {
+    proposal_with_metadata(dao, member, applicant, amount, metadata("Funding proposal"))
+}
+
+pub fn proposal_with_quorum(
+    dao: &Program,
+    member: u64,
+    applicant: u64,
+    amount: u128,
+    quorum: u128,
+) -> RunResult {
     dao.send(
         member,
         DaoAction::SubmitFundingProposal {
             applicant: applicant.into(),
             amount,
+            quorum,
+            metadata: metadata("Funding proposal"),
+        },
+    )
+}
+
+pub fn proposal_with_metadata(
+    dao: &Program,
+    member: u64,
+    applicant: u64,
+    amount: u128,
+    metadata: ProposalMetadata,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitFundingProposal {
+            applicant: applicant.into(),
+            amount,
+            quorum: 80,
+            metadata,
+        },
+    )
+}
+
+pub fn optimistic_proposal(dao: &Program, member: u64, applicant: u64, amount: u128) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitOptimisticProposal {
+            applicant: applicant.into(),
+            amount,
+            metadata: metadata("Optimistic proposal"),
+        },
+    )
+}
+
+pub fn execute_proposal(
+    dao: &Program,
+    member: u64,
+    destination: u64,
+    payload: Vec<u8>,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitExecuteProposal {
+            destination: destination.into(),
+            payload,
+            value: 0,
+            gas_limit: 10_000_000_000,
+            quorum: 80,
+            metadata: metadata("Execution proposal"),
+        },
+    )
+}
+
+pub fn batch_proposal(
+    dao: &Program,
+    member: u64,
+    actions: Vec<ProposalAction>,
+    halt_on_failure: bool,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitBatchProposal {
+            actions,
+            halt_on_failure,
+            quorum: 80,
+            metadata: metadata("Batch proposal"),
+        },
+    )
+}
+
+pub fn milestone_proposal(
+    dao: &Program,
+    member: u64,
+    applicant: u64,
+    amounts: &[u128],
+    reviewer: u64,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitMilestoneProposal {
+            applicant: applicant.into(),
+            milestones: amounts
+                .iter()
+                .map(|amount| Milestone {
+                    amount: *amount,
+                    details: "Milestone".to_string(),
+                })
+                .collect(),
+            reviewer: reviewer.into(),
+            quorum: 80,
+            metadata: metadata("Milestone proposal"),
+        },
+    )
+}
+
+pub fn stream_proposal(
+    dao: &Program,
+    member: u64,
+    applicant: u64,
+    amount: u128,
+    duration: u64,
+    cliff: u64,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitStreamProposal {
+            applicant: applicant.into(),
+            amount,
+            duration,
+            cliff,
+            quorum: 80,
+            metadata: metadata("Stream proposal"),
+        },
+    )
+}
+
+pub fn claim_stream(dao: &Program, recipient: u64, stream_id: u128) -> RunResult {
+    dao.send(recipient, DaoAction::ClaimStream { stream_id })
+}
+
+pub fn recurring_proposal(
+    dao: &Program,
+    member: u64,
+    applicant: u64,
+    amount: u128,
+    interval: u64,
+    periods: u32,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitRecurringProposal {
+            applicant: applicant.into(),
+            amount,
+            interval,
+            periods,
             quorum: 80,
-            details: "Funding proposal".to_string(),
+            metadata: metadata("Recurring proposal"),
         },
     )
 }
 
+pub fn claim_recurring_payment(dao: &Program, recipient: u64, payment_id: u128) -> RunResult {
+    dao.send(recipient, DaoAction::ClaimRecurringPayment { payment_id })
+}
+
+pub fn signal_proposal(dao: &Program, member: u64) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitSignalProposal {
+            metadata: metadata("Signal proposal"),
+            quorum: 50,
+        },
+    )
+}
+
+pub fn choice_proposal(
+    dao: &Program,
+    member: u64,
+    options: Vec<ChoiceOption>,
+    method: TallyMethod,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitChoiceProposal {
+            options,
+            method,
+            quorum: 50,
+            metadata: metadata("Choice proposal"),
+        },
+    )
+}
+
+pub fn ballot(dao: &Program, member: u64, proposal_id: u128, ballot: Ballot) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitBallot {
+            proposal_id,
+            ballot,
+        },
+    )
+}
+
+pub fn release_milestone(dao: &Program, reviewer: u64, proposal_id: u128, index: u32) -> RunResult {
+    dao.send(reviewer, DaoAction::ReleaseMilestone { proposal_id, index })
+}
+
 pub fn vote(dao: &Program, member: u64, proposal_id: u128, vote: Vote) -> RunResult {
     dao.send(member, DaoAction::SubmitVote { proposal_id, vote })
 }
 
+/// The sr25519 key of the member voting with `SubmitVoteBySig`, its account is funded by the token mock
+pub fn sr25519_signer() -> schnorrkel::Keypair {
+    schnorrkel::MiniSecretKey::from_bytes(&[7; 32])
+        .expect("Invalid secret key")
+        .expand_to_keypair(schnorrkel::ExpansionMode::Ed25519)
+}
+
+/// The ed25519 key of the member voting with `SubmitVoteBySig`, its account is funded by the token mock
+pub fn ed25519_signer() -> ed25519_dalek::SigningKey {
+    ed25519_dalek::SigningKey::from_bytes(&[8; 32])
+}
+
+pub fn submit_votes(
+    dao: &Program,
+    member: u64,
+    votes: Vec<(u128, Vote)>,
+    all_or_nothing: bool,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitVotes {
+            votes,
+            all_or_nothing,
+        },
+    )
+}
+
+pub fn commit_vote(
+    dao: &Program,
+    member: u64,
+    proposal_id: u128,
+    vote: Vote,
+    salt: [u8; 32],
+) -> RunResult {
+    let commitment = vote_commitment(proposal_id, &member.into(), &vote, &salt);
+    dao.send(
+        member,
+        DaoAction::CommitVote {
+            proposal_id,
+            commitment,
+        },
+    )
+}
+
+pub fn reveal_vote(
+    dao: &Program,
+    member: u64,
+    proposal_id: u128,
+    vote: Vote,
+    salt: [u8; 32],
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::RevealVote {
+            proposal_id,
+            vote,
+            salt,
+        },
+    )
+}
+
+pub fn vote_by_sig(
+    dao: &Program,
+    relayer: u64,
+    proposal_id: u128,
+    vote: Vote,
+    voter: [u8; 32],
+    nonce: u64,
+    signature: VoteSignature,
+) -> RunResult {
+    dao.send(
+        relayer,
+        DaoAction::SubmitVoteBySig {
+            proposal_id,
+            vote,
+            voter: voter.into(),
+            nonce,
+            signature,
+        },
+    )
+}
+
+pub fn vote_with_rationale(
+    dao: &Program,
+    member: u64,
+    proposal_id: u128,
+    vote: Vote,
+    rationale: VoteRationale,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitVoteWithRationale {
+            proposal_id,
+            vote,
+            rationale,
+        },
+    )
+}
+
 pub fn process(dao: &Program, member: u64, proposal_id: u128) -> RunResult {
     dao.send(member, DaoAction::ProcessProposal { proposal_id })
 }
 
+pub fn execute(dao: &Program, member: u64, proposal_id: u128) -> RunResult {
+    dao.send(member, DaoAction::ExecuteProposal { proposal_id })
+}
+
+pub fn veto(dao: &Program, guardian: u64, proposal_id: u128) -> RunResult {
+    dao.send(guardian, DaoAction::VetoProposal { proposal_id })
+}
+
+pub fn pause(dao: &Program, guardian: u64, actions: Vec<PausableAction>) -> RunResult {
+    dao.send(guardian, DaoAction::Pause { actions })
+}
+
+pub fn unpause(dao: &Program, guardian: u64, actions: Vec<PausableAction>) -> RunResult {
+    dao.send(guardian, DaoAction::Unpause { actions })
+}
+
 pub fn ragequit(dao: &Program, member: u64, amount: u128) -> RunResult {
-    dao.send(member, DaoAction::RageQuit { amount })
+    ragequit_with_min_funds(dao, member, amount, 0)
+}
+
+pub fn ragequit_with_min_funds(
+    dao: &Program,
+    member: u64,
+    amount: u128,
+    min_funds: u128,
+) -> RunResult {
+    dao.send(member, DaoAction::RageQuit { amount, min_funds })
+}
+
+pub fn claim_exit(dao: &Program, member: u64, min_funds: u128) -> RunResult {
+    dao.send(member, DaoAction::ClaimExit { min_funds })
+}
+
+/// Reads the result of the state function `fn_name` of the state crate
+pub fn read_state_using_wasm<E: Encode, D: Decode>(dao: &Program, fn_name: &str, argument: E) -> D {
+    let wasm = std::fs::read(STATE_WASM).expect("Unable to read the state wasm");
+    dao.read_state_using_wasm(fn_name, wasm, Some(argument))
+        .expect("Unable to read the state")
+}
+
+pub fn continue_transaction(dao: &Program, member: u64, tx_id: u64) -> RunResult {
+    dao.send(member, DaoAction::ContinueTransaction { tx_id })
+}
+
+pub fn settle_transaction(
+    dao: &Program,
+    guardian: u64,
+    tx_id: u64,
+    transferred: bool,
+) -> RunResult {
+    dao.send(
+        guardian,
+        DaoAction::SettleTransaction { tx_id, transferred },
+    )
+}
+
+pub fn resume_execution(dao: &Program, member: u64, proposal_id: u128) -> RunResult {
+    dao.send(member, DaoAction::ResumeExecution { proposal_id })
+}
+
+pub fn cancel_execution(dao: &Program, member: u64, proposal_id: u128) -> RunResult {
+    dao.send(member, DaoAction::CancelExecution { proposal_id })
+}
+
+pub fn conviction_proposal(
+    dao: &Program,
+    member: u64,
+    beneficiary: u64,
+    amount: u128,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::SubmitConvictionProposal {
+            beneficiary: beneficiary.into(),
+            amount,
+            metadata: metadata("Conviction proposal"),
+        },
+    )
+}
+
+pub fn stake_conviction(dao: &Program, member: u64, proposal_id: u128, amount: u128) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::StakeConviction {
+            proposal_id,
+            amount,
+        },
+    )
+}
+
+pub fn withdraw_conviction(
+    dao: &Program,
+    member: u64,
+    proposal_id: u128,
+    amount: u128,
+) -> RunResult {
+    dao.send(
+        member,
+        DaoAction::WithdrawConviction {
+            proposal_id,
+            amount,
+        },
+    )
+}
+
+pub fn execute_conviction(dao: &Program, member: u64, proposal_id: u128) -> RunResult {
+    dao.send(member, DaoAction::ExecuteConvictionProposal { proposal_id })
+}
+
+pub fn query_state(dao: &Program, from: u64, query: StateQuery) -> RunResult {
+    dao.send(from, DaoAction::QueryState { query })
 }